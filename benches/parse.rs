@@ -0,0 +1,52 @@
+use criterion::{criterion_group, criterion_main, Criterion};
+use solhop_types::dimacs::parse_dimacs_from_buf_reader;
+
+/// Generates a random k-CNF formula in dimacs format.
+fn generate_cnf(n_vars: usize, n_clauses: usize, k: usize) -> String {
+    let mut seed = 0x2545_f491_4f6c_dd1du64;
+    let mut next = move || {
+        seed ^= seed << 13;
+        seed ^= seed >> 7;
+        seed ^= seed << 17;
+        seed
+    };
+    let mut s = format!("p cnf {} {}\n", n_vars, n_clauses);
+    for _ in 0..n_clauses {
+        for _ in 0..k {
+            let r = next();
+            let var = (r % n_vars as u64) as i64 + 1;
+            let lit = if r & (1 << 63) == 0 { var } else { -var };
+            s.push_str(&lit.to_string());
+            s.push(' ');
+        }
+        s.push_str("0\n");
+    }
+    s
+}
+
+fn bench_parse(c: &mut Criterion) {
+    let cnf = generate_cnf(100_000, 1_000_000, 3);
+    let long = generate_cnf(100_000, 1_000_000, 10);
+    let mut group = c.benchmark_group("parse");
+    group.sample_size(10);
+    group.bench_function("cnf_1m_clauses", |b| {
+        b.iter(|| parse_dimacs_from_buf_reader(&mut cnf.as_bytes()).unwrap())
+    });
+    group.bench_function("cnf_10m_literals", |b| {
+        b.iter(|| parse_dimacs_from_buf_reader(&mut long.as_bytes()).unwrap())
+    });
+    #[cfg(feature = "rayon")]
+    {
+        use solhop_types::dimacs::{parse_dimacs_parallel, ParseOptions};
+        group.bench_function("cnf_1m_clauses_parallel", |b| {
+            b.iter(|| parse_dimacs_parallel(cnf.as_bytes(), ParseOptions::default()).unwrap())
+        });
+        group.bench_function("cnf_10m_literals_parallel", |b| {
+            b.iter(|| parse_dimacs_parallel(long.as_bytes(), ParseOptions::default()).unwrap())
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_parse);
+criterion_main!(benches);
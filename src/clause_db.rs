@@ -0,0 +1,499 @@
+use crate::dimacs::Dimacs;
+use crate::{n_vars_covering, Clause, IntoLit, Lit, Var};
+use std::ops::Index;
+
+/// In-memory cnf formula.
+#[derive(Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ClauseDb {
+    n_vars: usize,
+    clauses: Vec<Clause>,
+}
+
+impl ClauseDb {
+    /// Create an empty clause database over `n_vars` variables.
+    pub fn new(n_vars: usize) -> Self {
+        ClauseDb {
+            n_vars,
+            clauses: vec![],
+        }
+    }
+
+    /// Build a clause database from a parsed formula. Weights of a wcnf are
+    /// dropped.
+    pub fn from_dimacs(dimacs: &Dimacs) -> Self {
+        let clauses = dimacs
+            .iter_clauses()
+            .map(|c| Clause { lits: c.to_vec() })
+            .collect();
+        let n_vars = dimacs.n_vars();
+        ClauseDb { n_vars, clauses }
+    }
+
+    /// Returns the database as a cnf with the same variables and clauses.
+    /// Inverse of [`ClauseDb::from_dimacs`] for a cnf.
+    pub fn to_dimacs(&self) -> Dimacs {
+        Dimacs::Cnf {
+            n_vars: self.n_vars,
+            clauses: self.clauses.iter().map(|c| c.lits.clone()).collect(),
+        }
+    }
+
+    /// Allocate a fresh variable, i.e. the variable following all variables
+    /// so far.
+    pub fn new_var(&mut self) -> Var {
+        self.n_vars += 1;
+        Var::from_index(self.n_vars - 1)
+    }
+
+    /// Add a clause. The number of variables grows to cover its literals.
+    pub fn add_clause(&mut self, lits: impl IntoIterator<Item = impl IntoLit>) {
+        let clause: Clause = lits.into_iter().map(IntoLit::into_lit).collect();
+        self.n_vars = self.n_vars.max(n_vars_covering(clause.iter()));
+        self.clauses.push(clause);
+    }
+
+    /// Encode `y <-> (x1 & ... & xn)` for a fresh variable `y` and return its
+    /// positive literal. The conjunction of no inputs is true.
+    pub fn encode_and(&mut self, inputs: &[Lit]) -> Lit {
+        let y = self.new_var().pos_lit();
+        for &x in inputs {
+            self.add_clause([!y, x]);
+        }
+        self.add_clause(std::iter::once(y).chain(inputs.iter().map(|&x| !x)));
+        y
+    }
+
+    /// Encode `y <-> (x1 | ... | xn)` for a fresh variable `y` and return its
+    /// positive literal. The disjunction of no inputs is false.
+    pub fn encode_or(&mut self, inputs: &[Lit]) -> Lit {
+        let y = self.new_var().pos_lit();
+        for &x in inputs {
+            self.add_clause([y, !x]);
+        }
+        self.add_clause(std::iter::once(!y).chain(inputs.iter().copied()));
+        y
+    }
+
+    /// Encode `y <-> (x1 ^ ... ^ xn)` for a fresh variable `y` and return its
+    /// positive literal. The exclusive or of no inputs is false.
+    ///
+    /// More than two inputs are encoded as a chain of binary exclusive ors,
+    /// each with its own fresh variable.
+    pub fn encode_xor(&mut self, inputs: &[Lit]) -> Lit {
+        let (&first, rest) = match inputs {
+            [] => {
+                let y = self.new_var().pos_lit();
+                self.add_clause([!y]);
+                return y;
+            }
+            [x] => {
+                let y = self.new_var().pos_lit();
+                self.add_clause([!y, *x]);
+                self.add_clause([y, !*x]);
+                return y;
+            }
+            [first, rest @ ..] => (first, rest),
+        };
+        rest.iter().fold(first, |a, &b| {
+            let y = self.new_var().pos_lit();
+            self.add_clause([!y, a, b]);
+            self.add_clause([!y, !a, !b]);
+            self.add_clause([y, !a, b]);
+            self.add_clause([y, a, !b]);
+            y
+        })
+    }
+
+    /// Add clauses allowing at most one of `lits` to be true.
+    ///
+    /// Uses the pairwise encoding for up to five literals and the sequential
+    /// encoding otherwise, see [`AmoEncoding`].
+    pub fn add_at_most_one(&mut self, lits: &[Lit]) {
+        let encoding = if lits.len() <= PAIRWISE_AMO_MAX {
+            AmoEncoding::Pairwise
+        } else {
+            AmoEncoding::Sequential
+        };
+        self.add_at_most_one_with(lits, encoding);
+    }
+
+    /// Add clauses allowing at most one of `lits` to be true, using the given
+    /// encoding. Fewer than two literals need no clauses.
+    pub fn add_at_most_one_with(&mut self, lits: &[Lit], encoding: AmoEncoding) {
+        if lits.len() < 2 {
+            return;
+        }
+        match encoding {
+            AmoEncoding::Pairwise => {
+                for (i, &a) in lits.iter().enumerate() {
+                    for &b in &lits[i + 1..] {
+                        self.add_clause([!a, !b]);
+                    }
+                }
+            }
+            AmoEncoding::Sequential => {
+                // s_i is true if one of the first i + 1 literals is true.
+                let n = lits.len();
+                let mut prev = self.new_var().pos_lit();
+                self.add_clause([!lits[0], prev]);
+                for &x in &lits[1..n - 1] {
+                    let s = self.new_var().pos_lit();
+                    self.add_clause([!x, s]);
+                    self.add_clause([!prev, s]);
+                    self.add_clause([!x, !prev]);
+                    prev = s;
+                }
+                self.add_clause([!lits[n - 1], !prev]);
+            }
+        }
+    }
+
+    /// Add clauses requiring exactly one of `lits` to be true, i.e. the clause
+    /// of all literals and an at-most-one constraint as in
+    /// [`ClauseDb::add_at_most_one`].
+    pub fn add_exactly_one(&mut self, lits: &[Lit]) {
+        self.add_clause(lits.iter().copied());
+        self.add_at_most_one(lits);
+    }
+
+    /// Add clauses allowing at most `k` of `lits` to be true.
+    ///
+    /// Uses the sequential counter encoding, which for `0 < k < n` literals
+    /// introduces `(n - 1) * k` auxiliary variables and `2 * n * k + n - 3 * k - 1`
+    /// clauses. For `k = 0` every literal is negated by a unit clause, and for
+    /// `k >= n` there is nothing to add.
+    pub fn add_at_most_k(&mut self, lits: &[Lit], k: usize) {
+        let n = lits.len();
+        if k >= n {
+            return;
+        }
+        if k == 0 {
+            for &x in lits {
+                self.add_clause([!x]);
+            }
+            return;
+        }
+        // prev[j] is true if more than j of the literals so far are true.
+        let mut prev: Vec<Lit> = (0..k).map(|_| self.new_var().pos_lit()).collect();
+        self.add_clause([!lits[0], prev[0]]);
+        for &s in &prev[1..] {
+            self.add_clause([!s]);
+        }
+        for &x in &lits[1..n - 1] {
+            let s: Vec<Lit> = (0..k).map(|_| self.new_var().pos_lit()).collect();
+            self.add_clause([!x, s[0]]);
+            for j in 0..k {
+                self.add_clause([!prev[j], s[j]]);
+                if j > 0 {
+                    self.add_clause([!x, !prev[j - 1], s[j]]);
+                }
+            }
+            self.add_clause([!x, !prev[k - 1]]);
+            prev = s;
+        }
+        self.add_clause([!lits[n - 1], !prev[k - 1]]);
+    }
+
+    /// Returns the number of clauses.
+    pub fn len(&self) -> usize {
+        self.clauses.len()
+    }
+
+    /// Returns true if there are no clauses.
+    pub fn is_empty(&self) -> bool {
+        self.clauses.is_empty()
+    }
+
+    /// Returns an iterator over the clauses.
+    pub fn iter(&self) -> std::slice::Iter<'_, Clause> {
+        self.clauses.iter()
+    }
+
+    /// Returns the number of variables.
+    pub fn num_vars(&self) -> usize {
+        self.n_vars
+    }
+
+    /// Returns the occurrence lists of the clauses.
+    pub fn occurrence_lists(&self) -> OccurrenceLists {
+        OccurrenceLists::build(self.clauses.iter().map(|c| &c.lits[..]))
+    }
+}
+
+/// Encoding of an at-most-one constraint over `n` literals.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AmoEncoding {
+    /// A binary clause for every pair of literals: no auxiliary variables and
+    /// `n * (n - 1) / 2` clauses.
+    Pairwise,
+    /// The sequential counter encoding: `n - 1` auxiliary variables and
+    /// `3 * n - 4` clauses, for `n >= 2`.
+    Sequential,
+}
+
+/// Largest number of literals for which [`ClauseDb::add_at_most_one`] uses
+/// the pairwise encoding.
+const PAIRWISE_AMO_MAX: usize = 5;
+
+/// For every literal, the indices of the clauses it occurs in.
+///
+/// Indexing with a literal gives its clause indices in increasing order, each
+/// listed once even if the literal is repeated in a clause. Literals of
+/// variables that never occur give an empty slice.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct OccurrenceLists {
+    lists: Vec<Vec<usize>>,
+}
+
+impl OccurrenceLists {
+    /// Build the occurrence lists of a parsed formula. Clause indices are
+    /// positions in its clause list, weights of a wcnf are ignored.
+    pub fn from_dimacs(dimacs: &Dimacs) -> Self {
+        OccurrenceLists::build(dimacs.iter_clauses())
+    }
+
+    /// The lists cover the literals that occur, see [`n_vars_covering`].
+    fn build<'a>(clauses: impl Iterator<Item = &'a [Lit]>) -> Self {
+        let mut lists = vec![];
+        for (i, clause) in clauses.enumerate() {
+            for lit in clause {
+                if lists.len() <= lit.index() {
+                    lists.resize(lit.index() + 1, vec![]);
+                }
+                let list = &mut lists[lit.index()];
+                if list.last() != Some(&i) {
+                    list.push(i);
+                }
+            }
+        }
+        OccurrenceLists { lists }
+    }
+}
+
+impl Index<Lit> for OccurrenceLists {
+    type Output = [usize];
+
+    fn index(&self, lit: Lit) -> &[usize] {
+        self.lists.get(lit.index()).map_or(&[], |list| &list[..])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Lit;
+
+    #[test]
+    fn from_dimacs_and_add() {
+        let dimacs = Dimacs::Cnf {
+            n_vars: 2,
+            clauses: vec![vec![Lit::from_dimacs(1), Lit::from_dimacs(-2)]],
+        };
+        let mut db = ClauseDb::from_dimacs(&dimacs);
+        assert_eq!(db.len(), 1);
+        assert_eq!(db.num_vars(), 2);
+        db.add_clause(Clause {
+            lits: vec![Lit::from_dimacs(5)],
+        });
+        assert_eq!(db.len(), 2);
+        assert_eq!(db.num_vars(), 5);
+        assert_eq!(db.iter().map(|c| c.lits.len()).sum::<usize>(), 3);
+    }
+
+    #[test]
+    fn to_dimacs_roundtrip() {
+        let dimacs = Dimacs::Cnf {
+            n_vars: 4,
+            clauses: vec![
+                vec![Lit::from_dimacs(1), Lit::from_dimacs(-2)],
+                vec![],
+                vec![Lit::from_dimacs(-3), Lit::from_dimacs(1)],
+            ],
+        };
+        let db = ClauseDb::from_dimacs(&dimacs);
+        assert_eq!(db.to_dimacs(), dimacs);
+        assert_eq!(ClauseDb::from_dimacs(&db.to_dimacs()), db);
+
+        let mut db = ClauseDb::new(0);
+        let x = db.new_var();
+        db.add_clause(vec![x.neg_lit()]);
+        db.new_var();
+        assert_eq!(
+            db.to_dimacs(),
+            Dimacs::Cnf {
+                n_vars: 2,
+                clauses: vec![vec![Lit::from_dimacs(-1)]],
+            }
+        );
+    }
+
+    #[test]
+    fn occurrence_lists() {
+        let lit = Lit::from_dimacs;
+        let dimacs = Dimacs::Cnf {
+            n_vars: 4,
+            clauses: vec![
+                vec![lit(1), lit(-2)],
+                vec![lit(-1), lit(2), lit(1)],
+                vec![lit(1), lit(1)],
+            ],
+        };
+        let occs = OccurrenceLists::from_dimacs(&dimacs);
+        assert_eq!(&occs[lit(1)], &[0, 1, 2]);
+        assert_eq!(&occs[lit(-1)], &[1]);
+        assert_eq!(&occs[lit(2)], &[1]);
+        assert_eq!(&occs[lit(-2)], &[0]);
+        assert!(occs[lit(4)].is_empty());
+        assert!(occs[lit(-9)].is_empty());
+        assert_eq!(ClauseDb::from_dimacs(&dimacs).occurrence_lists(), occs);
+    }
+
+    #[test]
+    fn add_clause_into_lit() {
+        let mut db = ClauseDb::new(0);
+        db.add_clause([1, -2, 3]);
+        db.add_clause(vec![crate::Var::from_index(3)]);
+        let clauses: Vec<_> = db.iter().cloned().collect();
+        assert_eq!(clauses[0].lits, Clause::from_dimacs_ints(&[1, -2, 3]).lits);
+        assert_eq!(clauses[1].lits, Clause::from_dimacs_ints(&[4]).lits);
+        assert_eq!(db.num_vars(), 4);
+    }
+
+    #[test]
+    fn new_var() {
+        let mut db = ClauseDb::new(2);
+        assert_eq!(db.new_var(), Var::from_index(2));
+        assert_eq!(db.num_vars(), 3);
+        db.add_clause([5]);
+        assert_eq!(db.new_var(), Var::from_index(5));
+        assert_eq!(db.num_vars(), 6);
+    }
+
+    /// Returns every model of the clauses, by brute force.
+    fn models(db: &ClauseDb) -> Vec<Vec<bool>> {
+        (0..1u32 << db.num_vars())
+            .map(|bits| {
+                (0..db.num_vars())
+                    .map(|i| bits & (1 << i) != 0)
+                    .collect::<Vec<_>>()
+            })
+            .filter(|model| db.iter().all(|c| crate::satisfies(model, &c.lits)))
+            .collect()
+    }
+
+    #[test]
+    fn gates() {
+        type Encode = fn(&mut ClauseDb, &[Lit]) -> Lit;
+        type Eval = fn(&[bool]) -> bool;
+        let gates: [(Encode, Eval); 3] = [
+            (ClauseDb::encode_and, |xs| xs.iter().all(|&x| x)),
+            (ClauseDb::encode_or, |xs| xs.iter().any(|&x| x)),
+            (ClauseDb::encode_xor, |xs| {
+                xs.iter().filter(|&&x| x).count() % 2 == 1
+            }),
+        ];
+        for &(encode, eval) in &gates {
+            for n in 0..4 {
+                let mut db = ClauseDb::new(n);
+                // Negate the last input to cover negative literals.
+                let inputs: Vec<Lit> = (0..n)
+                    .map(|i| Lit::new(Var::from_index(i), i + 1 == n))
+                    .collect();
+                let y = encode(&mut db, &inputs);
+                let models = models(&db);
+                // Every input assignment extends to exactly one model.
+                assert_eq!(models.len(), 1 << n);
+                for model in &models {
+                    let xs: Vec<bool> = inputs
+                        .iter()
+                        .map(|&x| crate::lit_value(model, x).unwrap())
+                        .collect();
+                    assert_eq!(crate::lit_value(model, y), Some(eval(&xs)));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn at_most_one() {
+        for &encoding in &[AmoEncoding::Pairwise, AmoEncoding::Sequential] {
+            for n in 0..6 {
+                let mut db = ClauseDb::new(n);
+                let lits: Vec<Lit> = (0..n)
+                    .map(|i| Lit::new(Var::from_index(i), i % 2 == 1))
+                    .collect();
+                db.add_at_most_one_with(&lits, encoding);
+                if n >= 2 {
+                    let (aux, clauses) = match encoding {
+                        AmoEncoding::Pairwise => (0, n * (n - 1) / 2),
+                        AmoEncoding::Sequential => (n - 1, 3 * n - 4),
+                    };
+                    assert_eq!((db.num_vars() - n, db.len()), (aux, clauses));
+                }
+                let mut allowed = std::collections::HashSet::new();
+                for model in models(&db) {
+                    let values: Vec<bool> = lits
+                        .iter()
+                        .map(|&l| crate::lit_value(&model, l).unwrap())
+                        .collect();
+                    assert!(values.iter().filter(|&&v| v).count() <= 1);
+                    allowed.insert(values);
+                }
+                // All assignments with at most one true literal remain.
+                assert_eq!(allowed.len(), n + 1);
+            }
+        }
+        let mut db = ClauseDb::new(8);
+        let lits: Vec<Lit> = Var::range(8).map(Var::pos_lit).collect();
+        db.add_at_most_one(&lits);
+        assert_eq!(db.num_vars(), 15);
+    }
+
+    /// Returns the distinct values of `lits` over all models.
+    fn projected_models(db: &ClauseDb, lits: &[Lit]) -> std::collections::HashSet<Vec<bool>> {
+        models(db)
+            .iter()
+            .map(|model| {
+                lits.iter()
+                    .map(|&l| crate::lit_value(model, l).unwrap())
+                    .collect()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn cardinality() {
+        for n in 0..5 {
+            let lits: Vec<Lit> = (0..n)
+                .map(|i| Lit::new(Var::from_index(i), i % 2 == 1))
+                .collect();
+            let all: Vec<Vec<bool>> = (0..1u32 << n)
+                .map(|bits| (0..n).map(|i| bits & (1 << i) != 0).collect())
+                .collect();
+            let count = |values: &Vec<bool>| values.iter().filter(|&&v| v).count();
+
+            let mut db = ClauseDb::new(n);
+            db.add_exactly_one(&lits);
+            let expected = all.iter().filter(|v| count(v) == 1).cloned().collect();
+            assert_eq!(projected_models(&db, &lits), expected);
+
+            for k in 0..n + 2 {
+                let mut db = ClauseDb::new(n);
+                db.add_at_most_k(&lits, k);
+                if 0 < k && k < n {
+                    let clauses = 2 * n * k + n - 3 * k - 1;
+                    assert_eq!((db.num_vars() - n, db.len()), ((n - 1) * k, clauses));
+                }
+                let expected = all.iter().filter(|v| count(v) <= k).cloned().collect();
+                assert_eq!(
+                    projected_models(&db, &lits),
+                    expected,
+                    "n = {}, k = {}",
+                    n,
+                    k
+                );
+            }
+        }
+    }
+}
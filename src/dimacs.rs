@@ -1,9 +1,24 @@
-use crate::{Lit, Var};
+use crate::clause_db::OccurrenceLists;
+use crate::{
+    canonicalize_lits, is_tautology_lits, lit_lvalue, n_vars_covering, satisfies, Clause, IntoLit,
+    LBool, Lit, Solution, Var,
+};
+use once_cell::sync::Lazy;
 use regex::Regex;
-use std::io::BufRead;
+use std::cell::RefCell;
+use std::collections::{BTreeMap, BTreeSet};
+use std::convert::TryFrom;
+use std::fmt;
+use std::io::{self, BufRead, Write};
+
+#[cfg(any(feature = "rayon", feature = "mmap"))]
+mod bytes;
+#[cfg(feature = "rayon")]
+pub use bytes::parse_dimacs_parallel;
 
 /// Dimacs formula.
 #[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Dimacs {
     /// Unweighted formula.
     Cnf {
@@ -17,104 +32,2898 @@ pub enum Dimacs {
         /// Number of variables.
         n_vars: usize,
         /// Clauses with their weights.
+        ///
+        /// Weights are non-negative, the parser rejects negative weights with
+        /// `DimacsError::InvalidWeight`. Soft clauses of weight 0 are kept
+        /// as given, they never contribute to the cost.
         clauses: Vec<(Vec<Lit>, u64)>,
         /// Weight corresponding to hard clause.
         hard_weight: Option<u64>,
     },
 }
 
-/// Parse dimacs from buffer reader.
-pub fn parse_dimacs_from_buf_reader<F>(reader: &mut F) -> Dimacs
-where
-    F: std::io::BufRead,
-{
-    let mut n_clauses = 0usize;
-    let mut n_vars = 0usize;
-    let mut clauses = vec![];
-    let mut weights: Vec<u64> = vec![];
-    let mut hard_weight = None;
-    let mut is_wcnf = false;
-
-    for line in reader.lines() {
-        let line = line.unwrap();
-        let line = line.trim();
-        if line.is_empty() {
-            continue;
-        }
-        if line.starts_with('c') {
-            continue;
-        } else if line.starts_with('p') {
-            let re_cnf = Regex::new(r"^p\s+cnf\s+(\d+)\s+(\d+)").unwrap();
-            let re_wcnf = Regex::new(r"^p\s+wcnf\s+(\d+)\s+(\d+)(?:\s+(\d+))?").unwrap();
-            if let Some(cap) = re_cnf.captures(&line) {
-                n_vars = cap[1].parse().unwrap();
-                n_clauses = cap[2].parse().unwrap();
-            } else if let Some(cap) = re_wcnf.captures(&line) {
-                is_wcnf = true;
-                n_vars = cap[1].parse().unwrap();
-                n_clauses = cap[2].parse().unwrap();
-                hard_weight = cap.get(3).map(|m| m.as_str().parse().unwrap()); // cap[3].parse().unwrap();
+/// Returns true if a clause with `weight` is hard, i.e. its weight equals the
+/// hard weight. A clause of any other weight is soft, and without a hard
+/// weight every clause is soft.
+pub(crate) fn is_hard(weight: u64, hard_weight: Option<u64>) -> bool {
+    hard_weight == Some(weight)
+}
+
+/// Grows `v` with `value` so that it covers both literals of `lit`'s variable,
+/// for vectors indexed by literal sized as [`n_vars_covering`] describes.
+fn grow_to_lit<T: Clone>(v: &mut Vec<T>, lit: Lit, value: T) {
+    if v.len() <= lit.index() {
+        v.resize(2 * lit.var_index() + 2, value);
+    }
+}
+
+/// Returns one more than the sum of `weights`. The parser rejects headerless
+/// wcnfs whose soft weights sum to `u64::MAX` or more, so this only saturates
+/// for weights that were not parsed.
+fn top_weight(weights: impl Iterator<Item = u64>) -> u64 {
+    weights.fold(1, u64::saturating_add)
+}
+
+impl Dimacs {
+    /// Returns the number of variables.
+    pub fn n_vars(&self) -> usize {
+        match self {
+            Dimacs::Cnf { n_vars, .. } | Dimacs::Wcnf { n_vars, .. } => *n_vars,
+        }
+    }
+
+    /// Returns the number of clauses.
+    pub fn n_clauses(&self) -> usize {
+        match self {
+            Dimacs::Cnf { clauses, .. } => clauses.len(),
+            Dimacs::Wcnf { clauses, .. } => clauses.len(),
+        }
+    }
+
+    /// Returns an iterator over the literals of every clause, ignoring weights.
+    pub fn iter_clauses(&self) -> impl Iterator<Item = &[Lit]> {
+        let (cnf, wcnf) = match self {
+            Dimacs::Cnf { clauses, .. } => (Some(clauses), None),
+            Dimacs::Wcnf { clauses, .. } => (None, Some(clauses)),
+        };
+        let cnf = cnf.into_iter().flatten().map(|c| &c[..]);
+        let wcnf = wcnf.into_iter().flatten().map(|(c, _)| &c[..]);
+        cnf.chain(wcnf)
+    }
+
+    /// Returns the weight of the hard clauses.
+    ///
+    /// This is the declared hard weight, or, without one, the sum of all
+    /// weights plus one, a weight no clause has, as in the old wcnf format where
+    /// the top weight is effectively infinite. The sum saturates at
+    /// `u64::MAX`. A cnf has no soft clauses and gives 1.
+    pub fn effective_hard_weight(&self) -> u64 {
+        match self {
+            Dimacs::Cnf { .. } => 1,
+            Dimacs::Wcnf { hard_weight, .. } => hard_weight
+                .unwrap_or_else(|| u64::try_from(self.total_soft_weight() + 1).unwrap_or(u64::MAX)),
+        }
+    }
+
+    /// Returns the sum of the weights of the soft clauses, 0 for a cnf.
+    ///
+    /// The sum is taken as `u128`, which cannot overflow for fewer than 2^64
+    /// clauses.
+    pub fn total_soft_weight(&self) -> u128 {
+        match self {
+            Dimacs::Cnf { .. } => 0,
+            Dimacs::Wcnf {
+                clauses,
+                hard_weight,
+                ..
+            } => clauses
+                .iter()
+                .filter(|(_, weight)| !is_hard(*weight, *hard_weight))
+                .map(|(_, weight)| u128::from(*weight))
+                .sum(),
+        }
+    }
+
+    /// Returns a formula with the clauses of `self` followed by those of
+    /// `other`, over `max(self.n_vars(), other.n_vars())` variables.
+    ///
+    /// Both formulas share the variable namespace, i.e. a variable is the same
+    /// variable in both. Use [`Dimacs::merge_disjoint`] to keep them apart.
+    ///
+    /// Returns None if one formula is a cnf and the other a wcnf, or if two
+    /// wcnfs have different hard weights.
+    pub fn merge(&self, other: &Dimacs) -> Option<Dimacs> {
+        self.concat(other, 0)
+    }
+
+    /// Like [`Dimacs::merge`], but the variables of `other` are shifted by
+    /// `self.n_vars()` so the formulas share no variable. Returns the merged
+    /// formula over `self.n_vars() + other.n_vars()` variables and the offset.
+    ///
+    /// Returns None like [`Dimacs::merge`], and if the variable count or a
+    /// shifted variable does not fit in a `usize`.
+    pub fn merge_disjoint(&self, other: &Dimacs) -> Option<(Dimacs, usize)> {
+        let offset = self.n_vars();
+        Some((self.concat(other, offset)?, offset))
+    }
+
+    fn concat(&self, other: &Dimacs, offset: usize) -> Option<Dimacs> {
+        // Both literals of a variable up to `usize::MAX >> 1` have an index.
+        let shift = |c: &[Lit]| -> Option<Vec<Lit>> {
+            c.iter()
+                .map(|lit| {
+                    let var = lit.var_index().checked_add(offset)?;
+                    (var <= usize::MAX >> 1).then(|| Lit::new(Var::from_index(var), lit.sign()))
+                })
+                .collect()
+        };
+        let n_vars = self.n_vars().max(other.n_vars().checked_add(offset)?);
+        match (self, other) {
+            (Dimacs::Cnf { clauses: a, .. }, Dimacs::Cnf { clauses: b, .. }) => {
+                let b = b.iter().map(|c| shift(c)).collect::<Option<Vec<_>>>()?;
+                Some(Dimacs::Cnf {
+                    n_vars,
+                    clauses: a.iter().cloned().chain(b).collect(),
+                })
             }
-        } else {
-            let re = Regex::new(r"(-?\d+)").unwrap();
-            let mut cl = vec![];
-            let mut weight = 0u64;
-            for (i, cap) in re.captures_iter(&line).enumerate() {
-                if i == 0 && is_wcnf {
-                    weight = cap[1].parse::<u64>().unwrap();
-                    continue;
+            (
+                Dimacs::Wcnf {
+                    clauses: a,
+                    hard_weight,
+                    ..
+                },
+                Dimacs::Wcnf {
+                    clauses: b,
+                    hard_weight: other_hard_weight,
+                    ..
+                },
+            ) if hard_weight == other_hard_weight => {
+                let b = b
+                    .iter()
+                    .map(|(c, w)| Some((shift(c)?, *w)))
+                    .collect::<Option<Vec<_>>>()?;
+                Some(Dimacs::Wcnf {
+                    n_vars,
+                    clauses: a.iter().cloned().chain(b).collect(),
+                    hard_weight: *hard_weight,
+                })
+            }
+            _ => None,
+        }
+    }
+
+    /// Returns the formula as a cnf.
+    ///
+    /// If `only_hard` is true, only the clauses of a wcnf whose weight equals
+    /// the hard weight are kept, and without a declared hard weight there are
+    /// none. Otherwise all clauses are kept with their weights stripped. A cnf
+    /// is returned unchanged.
+    pub fn to_cnf(&self, only_hard: bool) -> Dimacs {
+        match self {
+            Dimacs::Cnf { .. } => self.clone(),
+            Dimacs::Wcnf {
+                n_vars,
+                clauses,
+                hard_weight,
+            } => Dimacs::Cnf {
+                n_vars: *n_vars,
+                clauses: clauses
+                    .iter()
+                    .filter(|(_, weight)| !only_hard || is_hard(*weight, *hard_weight))
+                    .map(|(c, _)| c.clone())
+                    .collect(),
+            },
+        }
+    }
+
+    /// Returns the implications of the binary clauses, `(!a, b)` and `(!b, a)`
+    /// for each clause `a ∨ b`, in clause order. These are the edges of the
+    /// implication graph. In a wcnf only hard clauses are considered.
+    pub fn binary_implications(&self) -> Vec<(Lit, Lit)> {
+        let mut implications = vec![];
+        for clause in self.hard_clauses() {
+            if let [a, b] = *clause {
+                implications.push((!a, b));
+                implications.push((!b, a));
+            }
+        }
+        implications
+    }
+
+    /// Iterates over the hard clauses, every clause of a cnf.
+    pub(crate) fn hard_clauses(&self) -> impl Iterator<Item = &[Lit]> {
+        let (cnf, wcnf) = match self {
+            Dimacs::Cnf { clauses, .. } => (Some(clauses), None),
+            Dimacs::Wcnf {
+                clauses,
+                hard_weight,
+                ..
+            } => (None, Some((clauses, *hard_weight))),
+        };
+        let cnf = cnf.into_iter().flatten().map(|c| &c[..]);
+        let wcnf = wcnf.into_iter().flat_map(|(clauses, hard_weight)| {
+            clauses
+                .iter()
+                .filter(move |(_, weight)| is_hard(*weight, hard_weight))
+                .map(|(c, _)| &c[..])
+        });
+        cnf.chain(wcnf)
+    }
+
+    /// Returns the variables occurring in at least one clause.
+    pub fn used_variables(&self) -> BTreeSet<Var> {
+        self.iter_clauses()
+            .flat_map(|c| c.iter().map(|lit| lit.var()))
+            .collect()
+    }
+
+    /// Returns for each literal, indexed by [`Lit::index`], the number of
+    /// clauses it occurs in. The vector covers the variables up to the
+    /// largest one occurring.
+    pub fn literal_counts(&self) -> Vec<usize> {
+        let mut counts = vec![];
+        self.for_each_occurrence(|lit, _| {
+            grow_to_lit(&mut counts, lit, 0);
+            counts[lit.index()] += 1;
+        });
+        counts
+    }
+
+    /// Returns the Jeroslow-Wang score of each literal, indexed by
+    /// [`Lit::index`]: the sum of `2^-len` over the clauses of length `len`
+    /// it occurs in. Covers the same literals as [`Dimacs::literal_counts`].
+    pub fn jeroslow_wang_scores(&self) -> Vec<f64> {
+        let mut scores = vec![];
+        self.for_each_occurrence(|lit, len| {
+            grow_to_lit(&mut scores, lit, 0.0);
+            scores[lit.index()] += (-(len as f64)).exp2();
+        });
+        scores
+    }
+
+    /// Calls `f` with every literal of every clause and the clause length,
+    /// counting repeated literals once per clause.
+    fn for_each_occurrence(&self, mut f: impl FnMut(Lit, usize)) {
+        let mut last_seen = vec![];
+        for (i, clause) in self.iter_clauses().enumerate() {
+            for &lit in clause {
+                grow_to_lit(&mut last_seen, lit, usize::MAX);
+                if last_seen[lit.index()] != i {
+                    last_seen[lit.index()] = i;
+                    f(lit, clause.len());
                 }
-                let l = match cap[1].parse::<i32>().unwrap() {
-                    0 => continue,
-                    n => n,
-                };
-                let var = Var::new((l.abs() - 1) as usize);
-                let lit = if l > 0 { var.pos_lit() } else { var.neg_lit() };
-                cl.push(lit);
             }
-            clauses.push(cl);
-            weights.push(weight);
-            if clauses.len() == n_clauses {
-                break;
+        }
+    }
+
+    /// Returns the pure literals, i.e. for every variable occurring with only
+    /// one sign that literal, ordered by variable.
+    pub fn pure_literals(&self) -> Vec<Lit> {
+        let mut occurs = vec![];
+        for &lit in self.iter_clauses().flatten() {
+            grow_to_lit(&mut occurs, lit, false);
+            occurs[lit.index()] = true;
+        }
+        occurs
+            .chunks(2)
+            .enumerate()
+            .filter_map(|(i, occurs)| match occurs {
+                [true, false] => Some(Var::from_index(i).pos_lit()),
+                [false, true] => Some(Var::from_index(i).neg_lit()),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// Returns true if every declared variable occurs in some clause.
+    pub fn is_dense(&self) -> bool {
+        // More variables than literals cannot all occur, and checking this
+        // first bounds the allocation by the size of the formula.
+        if self.iter_clauses().map(|c| c.len()).sum::<usize>() < self.n_vars() {
+            return false;
+        }
+        let mut used = vec![false; self.n_vars()];
+        for lit in self.iter_clauses().flatten() {
+            if let Some(used) = used.get_mut(lit.var_index()) {
+                *used = true;
             }
         }
+        used.iter().all(|&used| used)
     }
 
-    if is_wcnf {
-        Dimacs::Wcnf {
-            n_vars,
-            clauses: clauses.into_iter().zip(weights).collect(),
-            hard_weight,
+    /// Renumbers the used variables to `0..k`, keeping their order.
+    ///
+    /// Returns the compacted formula and, for each new variable, the original
+    /// variable it stands for, which translates a model of the compacted
+    /// formula back. A wcnf keeps its weights and hard weight.
+    pub fn compact(&self) -> (Dimacs, Vec<Var>) {
+        let vars: Vec<Var> = self.used_variables().into_iter().collect();
+        let mut new_index = std::collections::HashMap::with_capacity(vars.len());
+        for (i, &var) in vars.iter().enumerate() {
+            new_index.insert(var, Var::from_index(i));
+        }
+        let rename = |c: &[Lit]| -> Vec<Lit> {
+            c.iter()
+                .map(|lit| Lit::new(new_index[&lit.var()], lit.sign()))
+                .collect()
+        };
+        let n_vars = vars.len();
+        let compacted = match self {
+            Dimacs::Cnf { clauses, .. } => Dimacs::Cnf {
+                n_vars,
+                clauses: clauses.iter().map(|c| rename(c)).collect(),
+            },
+            Dimacs::Wcnf {
+                clauses,
+                hard_weight,
+                ..
+            } => Dimacs::Wcnf {
+                n_vars,
+                clauses: clauses.iter().map(|(c, w)| (rename(c), *w)).collect(),
+                hard_weight: *hard_weight,
+            },
+        };
+        (compacted, vars)
+    }
+
+    /// Returns true if both formulas have the same clauses up to order.
+    ///
+    /// Clauses are compared as multisets of canonical clauses, see
+    /// [`Clause::canonical`], so the order of clauses and of literals within a
+    /// clause is ignored. Both formulas must be of the same kind with the same
+    /// number of variables, a wcnf must also agree on the hard weight and on
+    /// the weight of every clause. The derived `PartialEq` compares exactly.
+    pub fn equivalent(&self, other: &Dimacs) -> bool {
+        fn canonical<'a>(clauses: impl Iterator<Item = (&'a [Lit], u64)>) -> Vec<(Clause, u64)> {
+            let mut clauses: Vec<_> = clauses
+                .map(|(lits, weight)| {
+                    let clause = Clause {
+                        lits: lits.to_vec(),
+                    };
+                    (clause.canonical(), weight)
+                })
+                .collect();
+            clauses.sort_by(|(a, wa), (b, wb)| a.lits.cmp(&b.lits).then(wa.cmp(wb)));
+            clauses
+        }
+        match (self, other) {
+            (Dimacs::Cnf { clauses: a, .. }, Dimacs::Cnf { clauses: b, .. }) => {
+                self.n_vars() == other.n_vars()
+                    && canonical(a.iter().map(|c| (&c[..], 0)))
+                        == canonical(b.iter().map(|c| (&c[..], 0)))
+            }
+            (
+                Dimacs::Wcnf {
+                    clauses: a,
+                    hard_weight: hard_a,
+                    ..
+                },
+                Dimacs::Wcnf {
+                    clauses: b,
+                    hard_weight: hard_b,
+                    ..
+                },
+            ) => {
+                self.n_vars() == other.n_vars()
+                    && hard_a == hard_b
+                    && canonical(a.iter().map(|(c, w)| (&c[..], *w)))
+                        == canonical(b.iter().map(|(c, w)| (&c[..], *w)))
+            }
+            _ => false,
+        }
+    }
+
+    /// Removes tautological clauses and repeated literals within the remaining
+    /// clauses, returning the number of clauses removed.
+    ///
+    /// A tautology is satisfied by every model, so dropping it never changes
+    /// the satisfiability or the cost of a model. In a wcnf this holds for
+    /// hard and soft clauses alike, and both are dropped. The order of the
+    /// remaining clauses and of their literals is preserved.
+    pub fn normalize(&mut self) -> usize {
+        // Returns false if the clause is a tautology.
+        fn normalize_clause(lits: &mut Vec<Lit>) -> bool {
+            let mut clause = Clause {
+                lits: std::mem::take(lits),
+            };
+            clause.dedup();
+            let keep = !clause.is_tautology();
+            *lits = clause.lits;
+            keep
+        }
+        let before = self.n_clauses();
+        match self {
+            Dimacs::Cnf { clauses, .. } => clauses.retain_mut(normalize_clause),
+            Dimacs::Wcnf { clauses, .. } => clauses.retain_mut(|(c, _)| normalize_clause(c)),
+        }
+        before - self.n_clauses()
+    }
+
+    /// Repeatedly assigns the literals of unit clauses, returning the
+    /// simplified formula and the implied literals in the order they were
+    /// derived, or None if a clause becomes empty.
+    ///
+    /// Satisfied clauses are removed and false literals struck from the
+    /// others, the variables keep their numbering. In a wcnf only hard clauses
+    /// propagate and only an empty hard clause is a conflict. Soft clauses
+    /// are simplified alike but kept when they become empty, so the cost of a
+    /// model extending the implied literals does not change.
+    pub fn unit_propagate(&self) -> Option<(Dimacs, Vec<Lit>)> {
+        let clauses: Vec<&[Lit]> = self.iter_clauses().collect();
+        let hard: Vec<bool> = match self {
+            Dimacs::Cnf { clauses, .. } => vec![true; clauses.len()],
+            Dimacs::Wcnf {
+                clauses,
+                hard_weight,
+                ..
+            } => clauses
+                .iter()
+                .map(|(_, weight)| is_hard(*weight, *hard_weight))
+                .collect(),
+        };
+        let n_vars = n_vars_covering(clauses.iter().copied().flatten());
+        let occurrences = OccurrenceLists::from_dimacs(self);
+        let mut values = vec![LBool::Undef; n_vars];
+        let mut implied = vec![];
+        let mut queue: Vec<usize> = (0..clauses.len()).filter(|&i| hard[i]).collect();
+        while let Some(i) = queue.pop() {
+            if !hard[i] {
+                continue;
+            }
+            // The clause is open if a literal is true or two distinct literals
+            // are unassigned, otherwise it is unit or empty.
+            let mut unit = None;
+            let mut open = false;
+            for &lit in clauses[i] {
+                match lit_lvalue(&values, lit) {
+                    LBool::False => {}
+                    LBool::Undef if unit.is_none() || unit == Some(lit) => unit = Some(lit),
+                    _ => {
+                        open = true;
+                        break;
+                    }
+                }
+            }
+            if open {
+                continue;
+            }
+            let unit = unit?;
+            values[unit.var_index()] = (!unit.sign()).into();
+            implied.push(unit);
+            queue.extend(&occurrences[!unit]);
+        }
+
+        let simplify = |c: &[Lit]| -> Option<Vec<Lit>> {
+            let mut lits = Vec::with_capacity(c.len());
+            for &lit in c {
+                match lit_lvalue(&values, lit) {
+                    LBool::True => return None,
+                    LBool::False => {}
+                    LBool::Undef => lits.push(lit),
+                }
+            }
+            Some(lits)
+        };
+        let simplified = match self {
+            Dimacs::Cnf { n_vars, clauses } => Dimacs::Cnf {
+                n_vars: *n_vars,
+                clauses: clauses.iter().filter_map(|c| simplify(c)).collect(),
+            },
+            Dimacs::Wcnf {
+                n_vars,
+                clauses,
+                hard_weight,
+            } => Dimacs::Wcnf {
+                n_vars: *n_vars,
+                clauses: clauses
+                    .iter()
+                    .filter_map(|(c, weight)| Some((simplify(c)?, *weight)))
+                    .collect(),
+                hard_weight: *hard_weight,
+            },
+        };
+        Some((simplified, implied))
+    }
+
+    /// Returns summary statistics of the formula.
+    pub fn stats(&self) -> DimacsStats {
+        let mut n_units = 0;
+        let mut max_clause_len = 0;
+        let mut n_lits = 0;
+        for clause in self.iter_clauses() {
+            if clause.len() == 1 {
+                n_units += 1;
+            }
+            max_clause_len = max_clause_len.max(clause.len());
+            n_lits += clause.len();
+        }
+        let n_clauses = self.n_clauses();
+        let n_soft = n_clauses - self.hard_clauses().count();
+        DimacsStats {
+            n_vars: self.n_vars(),
+            n_clauses,
+            n_units,
+            max_clause_len,
+            avg_clause_len: if n_clauses == 0 {
+                0.0
+            } else {
+                n_lits as f64 / n_clauses as f64
+            },
+            n_hard: n_clauses - n_soft,
+            n_soft,
+            total_soft_weight: self.total_soft_weight(),
+        }
+    }
+
+    /// Returns true if some clause is empty.
+    ///
+    /// A cnf with an empty clause is unsatisfiable. In a wcnf this looks at
+    /// all clauses, but only an empty hard clause makes it unsatisfiable.
+    pub fn has_empty_clause(&self) -> bool {
+        self.iter_clauses().any(|c| c.is_empty())
+    }
+
+    /// Returns true if every clause has at most one positive literal.
+    ///
+    /// Like the other classifications, this looks at all clauses of a wcnf
+    /// and ignores their weights.
+    pub fn is_horn(&self) -> bool {
+        self.iter_clauses()
+            .all(|c| c.iter().filter(|lit| !lit.sign()).count() <= 1)
+    }
+
+    /// Returns true if every clause has at most one negative literal.
+    pub fn is_dual_horn(&self) -> bool {
+        self.iter_clauses()
+            .all(|c| c.iter().filter(|lit| lit.sign()).count() <= 1)
+    }
+
+    /// Returns true if every clause has at most two literals.
+    pub fn is_2sat(&self) -> bool {
+        self.iter_clauses().all(|c| c.len() <= 2)
+    }
+
+    /// Returns the number of clauses of each length that occurs.
+    pub fn length_histogram(&self) -> BTreeMap<usize, usize> {
+        let mut histogram = BTreeMap::new();
+        for clause in self.iter_clauses() {
+            *histogram.entry(clause.len()).or_insert(0) += 1;
+        }
+        histogram
+    }
+
+    /// Returns the total weight of the soft clauses falsified by `model`, or
+    /// `None` if a hard clause is falsified.
+    ///
+    /// Weights are summed as `u128`, so the cost cannot overflow. Every clause
+    /// of a cnf is hard, in which case the cost is 0 for satisfying models.
+    /// Variables outside the model count as false.
+    pub fn cost(&self, model: &[bool]) -> Option<u128> {
+        match self {
+            Dimacs::Cnf { clauses, .. } => {
+                if clauses.iter().all(|c| satisfies(model, c)) {
+                    Some(0)
+                } else {
+                    None
+                }
+            }
+            Dimacs::Wcnf {
+                clauses,
+                hard_weight,
+                ..
+            } => {
+                let mut cost = 0u128;
+                for (clause, weight) in clauses {
+                    if satisfies(model, clause) {
+                        continue;
+                    }
+                    if is_hard(*weight, *hard_weight) {
+                        return None;
+                    }
+                    cost += u128::from(*weight);
+                }
+                Some(cost)
+            }
         }
-    } else {
-        Dimacs::Cnf { n_vars, clauses }
     }
 }
 
-/// Parse a cnf/wcnf dimacs file.
-pub fn parse_dimacs_from_file(filename: &std::path::Path) -> Dimacs {
-    let file = std::fs::File::open(filename).expect("File not found");
-    let mut reader = std::io::BufReader::new(file);
-    parse_dimacs_from_buf_reader(&mut reader)
+/// Summary statistics of a formula, see [`Dimacs::stats`].
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct DimacsStats {
+    /// Number of variables.
+    pub n_vars: usize,
+    /// Number of clauses.
+    pub n_clauses: usize,
+    /// Number of clauses with exactly one literal.
+    pub n_units: usize,
+    /// Length of the longest clause, 0 if there are no clauses.
+    pub max_clause_len: usize,
+    /// Average clause length, 0 if there are no clauses.
+    pub avg_clause_len: f64,
+    /// Number of hard clauses. Every clause of a cnf is hard.
+    pub n_hard: usize,
+    /// Number of soft clauses.
+    pub n_soft: usize,
+    /// Sum of the weights of the soft clauses.
+    pub total_soft_weight: u128,
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    #[test]
-    fn it_works() {
-        let wcnf = "p wcnf 1 2\n\
-        2 1 0\n\
-        3 -1 0
-        ";
-        let var_1 = Var::new(0);
-        assert_eq!(
-            parse_dimacs_from_buf_reader(&mut std::io::BufReader::new(wcnf.as_bytes())),
+/// Builder for constructing a [`Dimacs`] programmatically.
+///
+/// The number of variables is tracked as the largest variable seen.
+#[derive(Clone, Debug)]
+pub struct DimacsBuilder {
+    dimacs: Dimacs,
+}
+
+impl DimacsBuilder {
+    /// Create a builder for a cnf.
+    pub fn new_cnf() -> Self {
+        DimacsBuilder {
+            dimacs: Dimacs::Cnf {
+                n_vars: 0,
+                clauses: vec![],
+            },
+        }
+    }
+
+    /// Create a builder for a wcnf whose hard clauses have `hard_weight`.
+    pub fn new_wcnf(hard_weight: u64) -> Self {
+        DimacsBuilder {
+            dimacs: Dimacs::Wcnf {
+                n_vars: 0,
+                clauses: vec![],
+                hard_weight: Some(hard_weight),
+            },
+        }
+    }
+
+    /// Add a clause, which is hard when building a wcnf.
+    pub fn add_clause(&mut self, lits: impl IntoIterator<Item = impl IntoLit>) -> &mut Self {
+        let lits = self.track_vars(lits);
+        match &mut self.dimacs {
+            Dimacs::Cnf { clauses, .. } => clauses.push(lits),
             Dimacs::Wcnf {
-                n_vars: 1,
-                hard_weight: None,
-                clauses: vec![(vec![var_1.pos_lit()], 2), (vec![var_1.neg_lit()], 3)]
+                clauses,
+                hard_weight,
+                ..
+            } => clauses.push((lits, hard_weight.unwrap_or(0))),
+        }
+        self
+    }
+
+    /// Add a soft clause with the given weight.
+    ///
+    /// # Panics
+    ///
+    /// Panics when building a cnf.
+    pub fn add_soft_clause(
+        &mut self,
+        weight: u64,
+        lits: impl IntoIterator<Item = impl IntoLit>,
+    ) -> &mut Self {
+        let lits = self.track_vars(lits);
+        match &mut self.dimacs {
+            Dimacs::Cnf { .. } => panic!("soft clause added to a cnf"),
+            Dimacs::Wcnf { clauses, .. } => clauses.push((lits, weight)),
+        }
+        self
+    }
+
+    /// Returns the built formula.
+    pub fn build(self) -> Dimacs {
+        self.dimacs
+    }
+
+    fn track_vars(&mut self, lits: impl IntoIterator<Item = impl IntoLit>) -> Vec<Lit> {
+        let lits: Vec<Lit> = lits.into_iter().map(IntoLit::into_lit).collect();
+        match &mut self.dimacs {
+            Dimacs::Cnf { n_vars, .. } | Dimacs::Wcnf { n_vars, .. } => {
+                *n_vars = (*n_vars).max(n_vars_covering(&lits))
             }
-        );
+        }
+        lits
+    }
+}
+
+impl fmt::Display for Dimacs {
+    /// Formats the formula in cnf/wcnf format, as written by [`write_dimacs`].
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fn write_lits(lits: &[Lit], f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            for lit in lits {
+                write!(f, "{} ", lit)?;
+            }
+            writeln!(f, "0")
+        }
+
+        match self {
+            Dimacs::Cnf { n_vars, clauses } => {
+                writeln!(f, "p cnf {} {}", n_vars, clauses.len())?;
+                for clause in clauses {
+                    write_lits(clause, f)?;
+                }
+            }
+            Dimacs::Wcnf {
+                n_vars,
+                clauses,
+                hard_weight,
+            } => {
+                write!(f, "p wcnf {} {}", n_vars, clauses.len())?;
+                if let Some(hard_weight) = hard_weight {
+                    write!(f, " {}", hard_weight)?;
+                }
+                writeln!(f)?;
+                for (clause, weight) in clauses {
+                    write!(f, "{} ", weight)?;
+                    write_lits(clause, f)?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+const BOM: char = '\u{feff}';
+
+static RE_CNF: Lazy<Regex> = Lazy::new(|| Regex::new(r"^p\s+cnf\s+(\d+)\s+(\d+)").unwrap());
+static RE_WCNF: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"^p\s+wcnf\s+(\d+)\s+(\d+)(?:\s+(\d+))?").unwrap());
+
+/// Error returned when a dimacs formula cannot be parsed.
+///
+/// Line numbers are 1-based.
+#[derive(Debug)]
+pub enum DimacsError {
+    /// Reading from the underlying source failed.
+    Io(std::io::Error),
+    /// The problem line is malformed or missing.
+    InvalidHeader {
+        /// Line on which the problem was detected.
+        line: usize,
+    },
+    /// A literal could not be parsed.
+    InvalidLiteral {
+        /// Line containing the literal.
+        line: usize,
+        /// The offending token.
+        token: String,
+    },
+    /// A clause weight could not be parsed.
+    InvalidWeight {
+        /// Line containing the weight.
+        line: usize,
+        /// The offending token.
+        token: String,
+    },
+    /// The input ended before the problem line or in the middle of a clause.
+    UnexpectedEof,
+    /// A literal refers to a variable beyond the declared variable count.
+    VariableOutOfRange {
+        /// Line containing the literal.
+        line: usize,
+        /// The offending variable.
+        var: Var,
+    },
+    /// The number of clauses differs from the declared clause count.
+    ClauseCountMismatch {
+        /// Number of clauses declared in the header.
+        declared: usize,
+        /// Number of clauses in the input.
+        actual: usize,
+    },
+    /// A line of solver output is neither a comment, status nor value line.
+    InvalidSolution {
+        /// Line on which the problem was detected.
+        line: usize,
+    },
+    /// Input other than comments follows the declared number of clauses.
+    TrailingData {
+        /// Line on which the trailing data starts.
+        line: usize,
+    },
+}
+
+impl fmt::Display for DimacsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DimacsError::Io(e) => write!(f, "i/o error: {}", e),
+            DimacsError::InvalidHeader { line } => write!(f, "invalid header on line {}", line),
+            DimacsError::InvalidLiteral { line, token } => {
+                write!(f, "invalid literal {:?} on line {}", token, line)
+            }
+            DimacsError::InvalidWeight { line, token } => {
+                write!(f, "invalid weight {:?} on line {}", token, line)
+            }
+            DimacsError::UnexpectedEof => write!(f, "unexpected end of input"),
+            DimacsError::VariableOutOfRange { line, var } => {
+                write!(
+                    f,
+                    "variable {} out of range on line {}",
+                    var.index() + 1,
+                    line
+                )
+            }
+            DimacsError::ClauseCountMismatch { declared, actual } => write!(
+                f,
+                "header declares {} clauses but input has {}",
+                declared, actual
+            ),
+            DimacsError::InvalidSolution { line } => {
+                write!(f, "invalid solution line {}", line)
+            }
+            DimacsError::TrailingData { line } => {
+                write!(f, "trailing data after last clause on line {}", line)
+            }
+        }
+    }
+}
+
+impl std::error::Error for DimacsError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            DimacsError::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<std::io::Error> for DimacsError {
+    fn from(e: std::io::Error) -> Self {
+        DimacsError::Io(e)
+    }
+}
+
+/// Options controlling how strictly dimacs input is checked.
+///
+/// The default is lenient and accepts everything the plain parsing functions
+/// accept, which does not include the headerless wcnf format.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ParseOptions {
+    /// Reject literals whose variable exceeds the declared variable count
+    /// with [`DimacsError::VariableOutOfRange`]. Has no effect on the
+    /// headerless wcnf format, which declares no variable count.
+    pub strict_var_range: bool,
+    /// Read the input to the end and fail with
+    /// [`DimacsError::ClauseCountMismatch`] if the number of clauses differs
+    /// from the declared count. Otherwise parsing stops after the declared
+    /// number of clauses. Has no effect on the headerless wcnf format.
+    pub check_clause_count: bool,
+    /// Sort the literals of each clause and remove duplicates, as
+    /// [`Clause::canonicalize`] does. Otherwise the literals are kept in input
+    /// order.
+    ///
+    /// [`Clause::canonicalize`]: crate::Clause::canonicalize
+    pub canonicalize_clauses: bool,
+    /// Skip clauses containing a literal and its negation. Skipped clauses
+    /// still count towards the declared number of clauses.
+    pub drop_tautologies: bool,
+    /// Fail with [`DimacsError::TrailingData`] if anything but comments and
+    /// blank lines follows the declared number of clauses. Implied by
+    /// `check_clause_count`, which reports such input as a count mismatch.
+    /// Has no effect on the headerless wcnf format.
+    pub check_trailing_data: bool,
+    /// Accept the headerless wcnf format of the MaxSAT Evaluations since
+    /// 2022, where hard clauses start with `h`. Such a formula gets the
+    /// largest variable seen as variable count and the sum of all soft
+    /// weights plus one as hard weight, soft weights summing to `u64::MAX` or
+    /// more fail with [`DimacsError::InvalidWeight`]. Otherwise input without a problem
+    /// line fails with [`DimacsError::InvalidHeader`], since the leading
+    /// literal of each clause would be read as a weight.
+    pub headerless_wcnf: bool,
+}
+
+impl ParseOptions {
+    /// Apply the clause options to `lits`, returning false if the clause is
+    /// to be skipped.
+    pub(crate) fn keep_clause(self, lits: &mut Vec<Lit>) -> bool {
+        let tautology = if self.canonicalize_clauses {
+            canonicalize_lits(lits)
+        } else {
+            self.drop_tautologies && is_tautology_lits(lits)
+        };
+        !(self.drop_tautologies && tautology)
+    }
+}
+
+/// Whitespace separated tokens of a line based format, skipping blank lines
+/// and `c` comment lines. A line starting with `%` ends the input, as in the
+/// SATLIB instances. A leading byte order mark is skipped and `\r\n` line
+/// endings are accepted.
+pub(crate) struct Tokens<R> {
+    reader: R,
+    line: String,
+    pos: usize,
+    line_no: usize,
+    comments: Option<Vec<String>>,
+    eof: bool,
+}
+
+impl<R: BufRead> Tokens<R> {
+    pub(crate) fn new(reader: R) -> Self {
+        Tokens::with_line(reader, String::new())
+    }
+
+    /// Like [`Tokens::new`], reading lines into the buffer `line`.
+    fn with_line(reader: R, mut line: String) -> Self {
+        line.clear();
+        Tokens {
+            reader,
+            line,
+            pos: 0,
+            line_no: 0,
+            comments: None,
+            eof: false,
+        }
+    }
+
+    /// Keep the text of comment lines instead of dropping them.
+    pub(crate) fn collect_comments(&mut self) {
+        self.comments = Some(vec![]);
+    }
+
+    /// Returns the 1-based number of the current line.
+    pub(crate) fn line_no(&self) -> usize {
+        self.line_no
+    }
+
+    /// Returns the token at the given byte range of the current line.
+    pub(crate) fn token(&self, (start, end): (usize, usize)) -> &str {
+        &self.line[start..end]
+    }
+
+    /// Advance to the next line that is neither blank nor a comment.
+    /// Returns false at the end of input.
+    pub(crate) fn next_line(&mut self) -> Result<bool, DimacsError> {
+        loop {
+            self.line.clear();
+            self.pos = 0;
+            if self.eof || self.reader.read_line(&mut self.line)? == 0 {
+                return Ok(false);
+            }
+            self.line_no += 1;
+            if self.line_no == 1 && self.line.starts_with(BOM) {
+                self.line.drain(..BOM.len_utf8());
+            }
+            let trimmed = self.line.trim_start();
+            if trimmed.starts_with('%') {
+                self.eof = true;
+                self.line.clear();
+                return Ok(false);
+            }
+            if let Some(text) = trimmed.strip_prefix('c') {
+                if let Some(comments) = &mut self.comments {
+                    let text = text.strip_prefix(' ').unwrap_or(text);
+                    comments.push(text.trim_end_matches(['\r', '\n']).to_string());
+                }
+                continue;
+            }
+            if trimmed.is_empty() {
+                continue;
+            }
+            return Ok(true);
+        }
+    }
+
+    /// Returns the byte range of the next token in `self.line`, reading new
+    /// lines as needed.
+    pub(crate) fn next_token(&mut self) -> Result<Option<(usize, usize)>, DimacsError> {
+        loop {
+            let bytes = self.line.as_bytes();
+            while self.pos < bytes.len() && bytes[self.pos].is_ascii_whitespace() {
+                self.pos += 1;
+            }
+            if self.pos < bytes.len() {
+                let start = self.pos;
+                while self.pos < bytes.len() && !bytes[self.pos].is_ascii_whitespace() {
+                    self.pos += 1;
+                }
+                return Ok(Some((start, self.pos)));
+            }
+            if !self.next_line()? {
+                return Ok(None);
+            }
+        }
+    }
+}
+
+/// Parse a dimacs literal, returning `None` for the terminating `0`.
+pub(crate) fn parse_lit(token: &str, line: usize) -> Result<Option<Lit>, DimacsError> {
+    let invalid_literal = || DimacsError::InvalidLiteral {
+        line,
+        token: token.to_string(),
+    };
+    match token.parse::<i32>().map_err(|_| invalid_literal())? {
+        0 => Ok(None),
+        l => Lit::try_from_dimacs(l)
+            .map(Some)
+            .map_err(|_| invalid_literal()),
+    }
+}
+
+/// Format declared in a dimacs header.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum DimacsFormat {
+    /// `p cnf`.
+    Cnf,
+    /// `p wcnf`.
+    Wcnf,
+}
+
+/// The problem line of a dimacs formula, see [`parse_dimacs_header`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct DimacsHeader {
+    /// Declared format.
+    pub format: DimacsFormat,
+    /// Declared number of variables.
+    pub n_vars: usize,
+    /// Declared number of clauses.
+    pub n_clauses: usize,
+    /// Declared hard weight, always None for a cnf.
+    pub hard_weight: Option<u64>,
+}
+
+/// A clause read by [`DimacsClauses`].
+#[derive(Debug, PartialEq, Clone)]
+pub struct ClauseItem {
+    /// Literals of the clause.
+    pub lits: Vec<Lit>,
+    /// Weight of the clause, `None` for unweighted formulas and for hard
+    /// clauses of the headerless wcnf format.
+    pub weight: Option<u64>,
+}
+
+/// Callbacks receiving a formula as it is parsed, see [`parse_dimacs_with`].
+pub trait DimacsVisitor {
+    /// Called once with the problem line, before any clause. The headerless
+    /// wcnf format declares nothing and reports 0 variables, 0 clauses and no
+    /// hard weight.
+    fn header(
+        &mut self,
+        format: DimacsFormat,
+        n_vars: usize,
+        n_clauses: usize,
+        hard_weight: Option<u64>,
+    );
+
+    /// Called for each clause. The weight is `None` for cnf clauses and for
+    /// the hard clauses of the headerless wcnf format. `lits` is only valid
+    /// during the call.
+    fn clause(&mut self, lits: &[Lit], weight: Option<u64>);
+}
+
+/// Streaming dimacs parser yielding one clause at a time.
+///
+/// The header is read when the iterator is created, clauses are only parsed
+/// as they are requested, so the whole formula is never held in memory.
+///
+/// The headerless wcnf format of the MaxSAT Evaluations since 2022 is not
+/// supported, as neither the variable count nor the hard weight are known
+/// before the whole formula has been read.
+pub struct DimacsClauses<R> {
+    tokens: Tokens<R>,
+    n_vars: usize,
+    n_clauses: usize,
+    hard_weight: Option<u64>,
+    is_wcnf: bool,
+    headerless: bool,
+    options: ParseOptions,
+    n_read: usize,
+    done: bool,
+    /// Sum of the soft weights of a headerless wcnf so far.
+    soft_weight: u64,
+}
+
+impl<R: BufRead> DimacsClauses<R> {
+    /// Read the header from `reader` and prepare to stream its clauses.
+    pub fn new(reader: R) -> Result<Self, DimacsError> {
+        DimacsClauses::with_options(reader, ParseOptions::default())
+    }
+
+    /// Like [`DimacsClauses::new`], checking the input according to `options`.
+    pub fn with_options(reader: R, options: ParseOptions) -> Result<Self, DimacsError> {
+        let clauses = DimacsClauses::open(reader, options)?;
+        if clauses.headerless {
+            return Err(DimacsError::InvalidHeader {
+                line: clauses.tokens.line_no,
+            });
+        }
+        Ok(clauses)
+    }
+
+    /// Like [`DimacsClauses::new`], but accepts the headerless wcnf format if
+    /// `options` enable it.
+    fn open(reader: R, options: ParseOptions) -> Result<Self, DimacsError> {
+        DimacsClauses::from_tokens(Tokens::new(reader), options)
+    }
+
+    fn from_tokens(tokens: Tokens<R>, options: ParseOptions) -> Result<Self, DimacsError> {
+        let mut clauses = DimacsClauses::before_header(tokens, options);
+        clauses.read_header()?;
+        Ok(clauses)
+    }
+
+    /// Prepare to read the header from `tokens`.
+    fn before_header(tokens: Tokens<R>, options: ParseOptions) -> Self {
+        DimacsClauses {
+            tokens,
+            n_vars: 0,
+            n_clauses: 0,
+            hard_weight: None,
+            is_wcnf: false,
+            headerless: false,
+            options,
+            n_read: 0,
+            done: false,
+            soft_weight: 0,
+        }
+    }
+
+    /// Number of variables declared in the header.
+    pub fn n_vars(&self) -> usize {
+        self.n_vars
+    }
+
+    /// Number of clauses declared in the header.
+    pub fn n_clauses(&self) -> usize {
+        self.n_clauses
+    }
+
+    /// Returns true if the formula is a wcnf.
+    pub fn is_weighted(&self) -> bool {
+        self.is_wcnf
+    }
+
+    /// Weight corresponding to hard clause, if declared.
+    pub fn hard_weight(&self) -> Option<u64> {
+        self.hard_weight
+    }
+
+    /// Returns the header that was read.
+    pub fn header(&self) -> DimacsHeader {
+        DimacsHeader {
+            format: if self.is_wcnf {
+                DimacsFormat::Wcnf
+            } else {
+                DimacsFormat::Cnf
+            },
+            n_vars: self.n_vars,
+            n_clauses: self.n_clauses,
+            hard_weight: self.hard_weight,
+        }
+    }
+
+    fn read_header(&mut self) -> Result<(), DimacsError> {
+        if !self.tokens.next_line()? {
+            return Err(DimacsError::UnexpectedEof);
+        }
+        let line_no = self.tokens.line_no;
+        let line = self.tokens.line.trim();
+        if !line.starts_with('p') {
+            if !self.options.headerless_wcnf {
+                return Err(DimacsError::InvalidHeader { line: line_no });
+            }
+            // Headerless wcnf, the current line holds the first clause.
+            self.is_wcnf = true;
+            self.headerless = true;
+            return Ok(());
+        }
+        let invalid_header = |_| DimacsError::InvalidHeader { line: line_no };
+        if let Some(cap) = RE_CNF.captures(line) {
+            self.n_vars = cap[1].parse().map_err(invalid_header)?;
+            self.n_clauses = cap[2].parse().map_err(invalid_header)?;
+        } else if let Some(cap) = RE_WCNF.captures(line) {
+            self.is_wcnf = true;
+            self.n_vars = cap[1].parse().map_err(invalid_header)?;
+            self.n_clauses = cap[2].parse().map_err(invalid_header)?;
+            self.hard_weight = match cap.get(3) {
+                Some(m) => Some(m.as_str().parse().map_err(invalid_header)?),
+                None => None,
+            };
+        } else {
+            return Err(DimacsError::InvalidHeader { line: line_no });
+        }
+        self.tokens.pos = self.tokens.line.len();
+        Ok(())
+    }
+
+    /// Read tokens up to the terminating `0` into `lits`, returning the weight
+    /// of the clause or None at the end of the input. A clause may span
+    /// several lines.
+    fn read_clause(&mut self, lits: &mut Vec<Lit>) -> Result<Option<Option<u64>>, DimacsError> {
+        lits.clear();
+        let mut weight = None;
+        let mut started = false;
+        loop {
+            let range = match self.tokens.next_token()? {
+                Some(range) => range,
+                None if !started => return Ok(None),
+                None => return Err(DimacsError::UnexpectedEof),
+            };
+            let line = self.tokens.line_no();
+            let token = self.tokens.token(range);
+            if self.is_wcnf && !started {
+                started = true;
+                if self.headerless && token == "h" {
+                    continue;
+                }
+                weight = Some(token.parse().map_err(|_| DimacsError::InvalidWeight {
+                    line,
+                    token: token.to_string(),
+                })?);
+                continue;
+            }
+            started = true;
+            let lit = match parse_lit(token, line)? {
+                Some(lit) => lit,
+                None => return Ok(Some(weight)),
+            };
+            if self.options.strict_var_range && !self.headerless && lit.var_index() >= self.n_vars {
+                return Err(DimacsError::VariableOutOfRange {
+                    line,
+                    var: lit.var(),
+                });
+            }
+            lits.push(lit);
+        }
+    }
+
+    /// Like [`Iterator::next`], reading the literals of the clause into `lits`
+    /// and returning its weight.
+    fn next_into(&mut self, lits: &mut Vec<Lit>) -> Option<Result<Option<u64>, DimacsError>> {
+        let check_count = self.options.check_clause_count && !self.headerless;
+        loop {
+            if self.done {
+                return None;
+            }
+            if !self.headerless && !check_count && self.n_read == self.n_clauses {
+                self.done = true;
+                if !self.options.check_trailing_data {
+                    return None;
+                }
+                return match self.tokens.next_token() {
+                    Ok(Some(_)) => Some(Err(DimacsError::TrailingData {
+                        line: self.tokens.line_no(),
+                    })),
+                    Ok(None) => None,
+                    Err(e) => Some(Err(e)),
+                };
+            }
+            match self.read_clause(lits) {
+                Ok(Some(weight)) => {
+                    self.n_read += 1;
+                    if !self.options.keep_clause(lits) {
+                        continue;
+                    }
+                    if let Some(weight) = weight.filter(|_| self.headerless) {
+                        // The hard weight is one more than the sum, so the
+                        // sum must stay below the largest weight.
+                        match self.soft_weight.checked_add(weight) {
+                            Some(sum) if sum < u64::MAX => self.soft_weight = sum,
+                            _ => {
+                                self.done = true;
+                                return Some(Err(DimacsError::InvalidWeight {
+                                    line: self.tokens.line_no(),
+                                    token: weight.to_string(),
+                                }));
+                            }
+                        }
+                    }
+                    return Some(Ok(weight));
+                }
+                Ok(None) => {
+                    self.done = true;
+                    if check_count && self.n_read != self.n_clauses {
+                        return Some(Err(DimacsError::ClauseCountMismatch {
+                            declared: self.n_clauses,
+                            actual: self.n_read,
+                        }));
+                    }
+                    return None;
+                }
+                Err(e) => {
+                    self.done = true;
+                    return Some(Err(e));
+                }
+            }
+        }
+    }
+}
+
+impl<R: BufRead> Iterator for DimacsClauses<R> {
+    type Item = Result<ClauseItem, DimacsError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let mut lits = vec![];
+        self.next_into(&mut lits)
+            .map(|weight| weight.map(|weight| ClauseItem { lits, weight }))
+    }
+
+    /// The upper bound is the number of clauses the header has left, unless
+    /// the end of the input is checked or there is no header. The input may
+    /// end early, so the lower bound is 0.
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let options = self.options;
+        if self.done {
+            (0, Some(0))
+        } else if self.headerless || options.check_clause_count || options.check_trailing_data {
+            (0, None)
+        } else {
+            (0, Some(self.n_clauses - self.n_read))
+        }
+    }
+}
+
+/// Parse dimacs from buffer reader.
+///
+/// This accepts the `p cnf` and `p wcnf` formats. The headerless wcnf format
+/// of the MaxSAT Evaluations since 2022 needs
+/// [`ParseOptions::headerless_wcnf`].
+///
+/// A line starting with `%` ends the formula, so the `%` and `0` trailer of
+/// the SATLIB instances is ignored.
+pub fn parse_dimacs_from_buf_reader<F>(reader: &mut F) -> Result<Dimacs, DimacsError>
+where
+    F: std::io::BufRead,
+{
+    parse_dimacs_with_options(reader, ParseOptions::default())
+}
+
+/// Read only the header of a formula, leaving the reader right after the
+/// problem line.
+///
+/// Comment and blank lines before the header are skipped. The headerless wcnf
+/// format has no header and is rejected with [`DimacsError::InvalidHeader`].
+pub fn parse_dimacs_header<R: BufRead>(reader: &mut R) -> Result<DimacsHeader, DimacsError> {
+    Ok(DimacsClauses::new(reader)?.header())
+}
+
+/// Parse dimacs from a string, see [`parse_dimacs_from_buf_reader`].
+pub fn parse_dimacs_from_str(s: &str) -> Result<Dimacs, DimacsError> {
+    parse_dimacs_from_buf_reader(&mut s.as_bytes())
+}
+
+/// Parse dimacs from a byte slice, see [`parse_dimacs_from_buf_reader`].
+///
+/// This is the recommended entry point for untrusted input, and a suitable
+/// fuzz target: every input gives a formula or an error, never a panic.
+/// Invalid UTF-8 is reported as [`DimacsError::Io`], numbers too large for
+/// their type as invalid tokens, and headerless soft weights too large to
+/// sum as invalid weights. Memory use is linear in the length of the input:
+/// the declared variable count allocates nothing, and room is reserved for
+/// at most one declared clause per two input bytes, the shortest clause
+/// being `0` and a separator.
+pub fn parse_dimacs_bytes(input: &[u8]) -> Result<Dimacs, DimacsError> {
+    let mut clauses = DimacsClauses::open(input, ParseOptions::default())?;
+    let max_reserved = MAX_RESERVED_CLAUSES.min(input.len() / 2 + 1);
+    collect_dimacs(&mut clauses, &mut vec![], max_reserved)
+}
+
+/// Parse dimacs from buffer reader, checking the input according to `options`.
+///
+/// See [`parse_dimacs_from_buf_reader`] for the accepted formats.
+pub fn parse_dimacs_with_options<F>(
+    reader: &mut F,
+    options: ParseOptions,
+) -> Result<Dimacs, DimacsError>
+where
+    F: std::io::BufRead,
+{
+    let mut clauses = DimacsClauses::open(reader, options)?;
+    collect_dimacs(&mut clauses, &mut vec![], MAX_RESERVED_CLAUSES)
+}
+
+/// Like [`parse_dimacs_with_options`], also returning the text of all comment
+/// lines in input order.
+///
+/// The leading `c` and one following space are stripped from each comment.
+/// Use [`write_dimacs_with_comments`] to emit them again.
+pub fn parse_dimacs_with_comments<F>(
+    reader: &mut F,
+    options: ParseOptions,
+) -> Result<(Dimacs, Vec<String>), DimacsError>
+where
+    F: std::io::BufRead,
+{
+    let mut tokens = Tokens::new(reader);
+    tokens.collect_comments();
+    let mut clauses = DimacsClauses::from_tokens(tokens, options)?;
+    let dimacs = collect_dimacs(&mut clauses, &mut vec![], MAX_RESERVED_CLAUSES)?;
+    // Comments may follow the last clause.
+    while clauses.tokens.next_line()? {}
+    Ok((dimacs, clauses.tokens.comments.take().unwrap_or_default()))
+}
+
+/// Parse a formula from `reader`, passing the header and each clause to
+/// `visitor` instead of collecting them into a [`Dimacs`].
+///
+/// Clauses are read into a single reused buffer, so memory use does not grow
+/// with the size of the input. On error the visitor may have seen part of the
+/// input. See [`parse_dimacs_from_buf_reader`] for the accepted formats.
+pub fn parse_dimacs_with<R: BufRead, V: DimacsVisitor>(
+    reader: R,
+    visitor: &mut V,
+) -> Result<(), DimacsError> {
+    visit_dimacs(
+        &mut DimacsClauses::open(reader, ParseOptions::default())?,
+        visitor,
+        &mut vec![],
+    )
+}
+
+/// Pass the clauses to `visitor`, reading each into the buffer `lits`.
+fn visit_dimacs<R: BufRead, V: DimacsVisitor>(
+    clauses: &mut DimacsClauses<R>,
+    visitor: &mut V,
+    lits: &mut Vec<Lit>,
+) -> Result<(), DimacsError> {
+    let header = clauses.header();
+    visitor.header(
+        header.format,
+        header.n_vars,
+        header.n_clauses,
+        header.hard_weight,
+    );
+    while let Some(weight) = clauses.next_into(lits) {
+        visitor.clause(lits, weight?);
+    }
+    Ok(())
+}
+
+/// Collect the clauses into a [`Dimacs`], reserving room for at most
+/// `max_reserved` of the declared clauses up front.
+fn collect_dimacs<R: BufRead>(
+    clauses: &mut DimacsClauses<R>,
+    lits: &mut Vec<Lit>,
+    max_reserved: usize,
+) -> Result<Dimacs, DimacsError> {
+    let mut collector = Collector {
+        max_reserved,
+        dimacs: Dimacs::Cnf {
+            n_vars: 0,
+            clauses: vec![],
+        },
+        headerless: clauses.headerless,
+        hard: vec![],
+    };
+    visit_dimacs(clauses, &mut collector, lits)?;
+    Ok(collector.finish())
+}
+
+/// Upper bound on the clauses reserved up front, so that a bogus header
+/// cannot exhaust memory.
+const MAX_RESERVED_CLAUSES: usize = 1 << 20;
+
+/// Visitor building the [`Dimacs`] returned by the parsing functions.
+struct Collector {
+    max_reserved: usize,
+    dimacs: Dimacs,
+    headerless: bool,
+    /// Positions of the hard clauses of a headerless wcnf, whose weight is
+    /// only known at the end.
+    hard: Vec<usize>,
+}
+
+impl Collector {
+    fn finish(self) -> Dimacs {
+        match self.dimacs {
+            Dimacs::Wcnf {
+                mut clauses,
+                n_vars,
+                ..
+            } if self.headerless => {
+                // Hard clauses still have weight 0 and do not add to the sum.
+                let top = top_weight(clauses.iter().map(|(_, w)| *w));
+                for &i in &self.hard {
+                    clauses[i].1 = top;
+                }
+                Dimacs::Wcnf {
+                    n_vars,
+                    clauses,
+                    hard_weight: Some(top),
+                }
+            }
+            dimacs => dimacs,
+        }
+    }
+}
+
+impl DimacsVisitor for Collector {
+    fn header(
+        &mut self,
+        format: DimacsFormat,
+        n_vars: usize,
+        n_clauses: usize,
+        hard_weight: Option<u64>,
+    ) {
+        let capacity = n_clauses.min(self.max_reserved);
+        self.dimacs = match format {
+            DimacsFormat::Cnf => Dimacs::Cnf {
+                n_vars,
+                clauses: Vec::with_capacity(capacity),
+            },
+            DimacsFormat::Wcnf => Dimacs::Wcnf {
+                n_vars,
+                clauses: Vec::with_capacity(capacity),
+                hard_weight,
+            },
+        };
+    }
+
+    fn clause(&mut self, lits: &[Lit], weight: Option<u64>) {
+        match &mut self.dimacs {
+            Dimacs::Cnf { clauses, .. } => clauses.push(lits.to_vec()),
+            Dimacs::Wcnf {
+                clauses, n_vars, ..
+            } => {
+                if self.headerless {
+                    if weight.is_none() {
+                        self.hard.push(clauses.len());
+                    }
+                    *n_vars = (*n_vars).max(n_vars_covering(lits));
+                }
+                clauses.push((lits.to_vec(), weight.unwrap_or(0)));
+            }
+        }
+    }
+}
+
+/// Reusable dimacs parser for parsing many formulas with the same options.
+///
+/// The parser owns the line buffer and the literal buffer a parse reads
+/// into, so that later parses reuse their allocations, also after an error.
+/// A parse started from a visitor of another parse gets fresh buffers. The
+/// header regexes are compiled once per process, creating a parser compiles
+/// them up front so that no parse pays for it.
+#[derive(Clone, Debug)]
+pub struct DimacsParser {
+    options: ParseOptions,
+    line: RefCell<String>,
+    lits: RefCell<Vec<Lit>>,
+}
+
+impl DimacsParser {
+    /// Create a parser with the default options.
+    pub fn new() -> Self {
+        DimacsParser::with_options(ParseOptions::default())
+    }
+
+    /// Create a parser checking the input according to `options`.
+    pub fn with_options(options: ParseOptions) -> Self {
+        Lazy::force(&RE_CNF);
+        Lazy::force(&RE_WCNF);
+        DimacsParser {
+            options,
+            line: RefCell::new(String::new()),
+            lits: RefCell::new(vec![]),
+        }
+    }
+
+    /// Returns the options of the parser.
+    pub fn options(&self) -> ParseOptions {
+        self.options
+    }
+
+    /// Parse a formula, see [`parse_dimacs_with_options`].
+    pub fn parse<R: BufRead>(&self, reader: R) -> Result<Dimacs, DimacsError> {
+        self.run(reader, |clauses, lits| {
+            collect_dimacs(clauses, lits, MAX_RESERVED_CLAUSES)
+        })
+    }
+
+    /// Parse a formula into `visitor`, see [`parse_dimacs_with`].
+    pub fn parse_with<R: BufRead, V: DimacsVisitor>(
+        &self,
+        reader: R,
+        visitor: &mut V,
+    ) -> Result<(), DimacsError> {
+        self.run(reader, |clauses, lits| visit_dimacs(clauses, visitor, lits))
+    }
+
+    /// Read the header and pass the clauses to `f`, lending it the buffers.
+    /// The buffers are taken out of their cells for the parse, so a nested
+    /// parse finds them empty instead of borrowed.
+    fn run<R: BufRead, T>(
+        &self,
+        reader: R,
+        f: impl FnOnce(&mut DimacsClauses<R>, &mut Vec<Lit>) -> Result<T, DimacsError>,
+    ) -> Result<T, DimacsError> {
+        let tokens = Tokens::with_line(reader, self.line.take());
+        let mut lits = self.lits.take();
+        let mut clauses = DimacsClauses::before_header(tokens, self.options);
+        let result = clauses
+            .read_header()
+            .and_then(|()| f(&mut clauses, &mut lits));
+        self.line.replace(std::mem::take(&mut clauses.tokens.line));
+        self.lits.replace(lits);
+        result
+    }
+}
+
+impl Default for DimacsParser {
+    fn default() -> Self {
+        DimacsParser::new()
+    }
+}
+
+/// Parse a cnf/wcnf dimacs file.
+///
+/// With the `gzip` feature, files with a `.gz` extension are decompressed.
+/// Failing to open the file, e.g. because it does not exist, is reported as
+/// `DimacsError::Io` carrying the OS error.
+pub fn parse_dimacs_from_file(filename: &std::path::Path) -> Result<Dimacs, DimacsError> {
+    let file = std::fs::File::open(filename)?;
+    #[cfg(feature = "gzip")]
+    {
+        if filename.extension().is_some_and(|ext| ext == "gz") {
+            let mut reader = std::io::BufReader::new(flate2::read::GzDecoder::new(file));
+            return parse_dimacs_from_buf_reader(&mut reader);
+        }
+    }
+    let mut reader = std::io::BufReader::new(file);
+    parse_dimacs_from_buf_reader(&mut reader)
+}
+
+/// Parse a cnf/wcnf dimacs file by mapping it into memory.
+///
+/// The mapped bytes are scanned in place instead of being copied line by
+/// line, the result is the same as with [`parse_dimacs_from_file`] on an
+/// uncompressed file. The file must not be modified while it is parsed.
+#[cfg(feature = "mmap")]
+pub fn parse_dimacs_mmap(filename: &std::path::Path) -> Result<Dimacs, DimacsError> {
+    let file = std::fs::File::open(filename)?;
+    // Safety: the map is only read during parsing, and modifying the file in
+    // the meantime is documented as unsupported.
+    let map = unsafe { memmap2::Mmap::map(&file)? };
+    bytes::parse_slice(&map, ParseOptions::default())
+}
+
+/// Write a dimacs formula in cnf/wcnf format.
+pub fn write_dimacs<W: Write>(dimacs: &Dimacs, writer: &mut W) -> io::Result<()> {
+    write!(writer, "{}", dimacs)
+}
+
+/// Write a dimacs formula preceded by the given comment lines.
+///
+/// Each comment is written as a `c` line before the header, so comments that
+/// appeared between clauses are moved to the top.
+pub fn write_dimacs_with_comments<W: Write>(
+    dimacs: &Dimacs,
+    comments: &[String],
+    writer: &mut W,
+) -> io::Result<()> {
+    for comment in comments {
+        if comment.is_empty() {
+            writeln!(writer, "c")?;
+        } else {
+            writeln!(writer, "c {}", comment)?;
+        }
+    }
+    write_dimacs(dimacs, writer)
+}
+
+/// Write a solution in the SAT competition output format.
+///
+/// A `Sat` model is printed as a single `v` line of signed variables
+/// terminated by `0`. A `Best` model was not proven to satisfy the formula,
+/// so it is reported as `s UNKNOWN` without a model.
+pub fn write_solution<W: Write>(sol: &Solution, w: &mut W) -> io::Result<()> {
+    match sol {
+        Solution::Sat(model) => {
+            writeln!(w, "s SATISFIABLE")?;
+            write!(w, "v")?;
+            for (i, &value) in model.iter().enumerate() {
+                let lit = Lit::new(Var::from_index(i), !value);
+                write!(w, " {}", lit)?;
+            }
+            writeln!(w, " 0")
+        }
+        Solution::Unsat => writeln!(w, "s UNSATISFIABLE"),
+        Solution::Best(_) | Solution::Unknown => writeln!(w, "s UNKNOWN"),
+    }
+}
+
+/// Parse solver output in the SAT competition format from buffer reader.
+///
+/// The `s` line gives the verdict and the `v` lines the model over `n_vars`
+/// variables, where a negative literal means false. Variables not listed are
+/// false.
+pub fn parse_solution_from_buf_reader<F>(
+    reader: &mut F,
+    n_vars: usize,
+) -> Result<Solution, DimacsError>
+where
+    F: std::io::BufRead,
+{
+    // Unsat and unknown results return right away, so only a satisfiable
+    // status needs to be remembered.
+    let mut satisfiable = false;
+    let mut model = vec![false; n_vars];
+    for (line_no, line) in reader.lines().enumerate() {
+        let line_no = line_no + 1;
+        let line = line?;
+        let mut tokens = line.split_ascii_whitespace();
+        match tokens.next() {
+            None | Some("c") => {}
+            Some("s") => match tokens.collect::<Vec<_>>()[..] {
+                ["SATISFIABLE"] => satisfiable = true,
+                ["UNSATISFIABLE"] => return Ok(Solution::Unsat),
+                ["UNKNOWN"] => return Ok(Solution::Unknown),
+                _ => return Err(DimacsError::InvalidSolution { line: line_no }),
+            },
+            Some("v") => {
+                for token in tokens {
+                    let lit = match parse_lit(token, line_no)? {
+                        Some(lit) => lit,
+                        None => continue,
+                    };
+                    match model.get_mut(lit.var_index()) {
+                        Some(value) => *value = !lit.sign(),
+                        None => {
+                            return Err(DimacsError::VariableOutOfRange {
+                                line: line_no,
+                                var: lit.var(),
+                            })
+                        }
+                    }
+                }
+            }
+            Some(_) => return Err(DimacsError::InvalidSolution { line: line_no }),
+        }
+    }
+    if satisfiable {
+        Ok(Solution::Sat(model))
+    } else {
+        Err(DimacsError::UnexpectedEof)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(s: &str) -> Result<Dimacs, DimacsError> {
+        parse_dimacs_from_str(s)
+    }
+
+    const HEADERLESS: ParseOptions = ParseOptions {
+        strict_var_range: false,
+        check_clause_count: false,
+        canonicalize_clauses: false,
+        drop_tautologies: false,
+        check_trailing_data: false,
+        headerless_wcnf: true,
+    };
+
+    fn parse_headerless(s: &str) -> Result<Dimacs, DimacsError> {
+        parse_dimacs_with_options(&mut s.as_bytes(), HEADERLESS)
+    }
+
+    #[test]
+    fn it_works() {
+        let wcnf = "p wcnf 1 2\n\
+        2 1 0\n\
+        3 -1 0
+        ";
+        let var_1 = Var::new(0);
+        assert_eq!(
+            parse(wcnf).unwrap(),
+            Dimacs::Wcnf {
+                n_vars: 1,
+                hard_weight: None,
+                clauses: vec![(vec![var_1.pos_lit()], 2), (vec![var_1.neg_lit()], 3)]
+            }
+        );
+    }
+
+    #[test]
+    fn errors_carry_line_numbers() {
+        match parse("c comment\np cnf 1 1\n99999999999 0\n") {
+            Err(DimacsError::InvalidLiteral { line, token }) => {
+                assert_eq!(line, 3);
+                assert_eq!(token, "99999999999");
+            }
+            r => panic!("unexpected result {:?}", r),
+        }
+        assert!(matches!(
+            parse("p dnf 1 1\n"),
+            Err(DimacsError::InvalidHeader { line: 1 })
+        ));
+        assert!(matches!(
+            DimacsClauses::new("c new format\n1 2 0\n".as_bytes()),
+            Err(DimacsError::InvalidHeader { line: 2 })
+        ));
+        assert!(matches!(parse("c only\n"), Err(DimacsError::UnexpectedEof)));
+        assert!(matches!(
+            parse("p cnf 1 1\n-2147483648 0\n"),
+            Err(DimacsError::InvalidLiteral { line: 2, .. })
+        ));
+        match parse("p cnf 2 1\n1 abc 0\n") {
+            Err(DimacsError::InvalidLiteral { line: 2, token }) => assert_eq!(token, "abc"),
+            r => panic!("unexpected result {:?}", r),
+        }
+    }
+
+    #[test]
+    fn errors_display() {
+        let err = DimacsError::InvalidLiteral {
+            line: 12,
+            token: "abc".to_string(),
+        };
+        assert_eq!(err.to_string(), "invalid literal \"abc\" on line 12");
+        let io = DimacsError::from(std::io::Error::other("boom"));
+        assert!(std::error::Error::source(&io).is_some());
+    }
+
+    #[test]
+    fn clause_spanning_lines() {
+        let cnf = "p cnf 3 2\n\
+        1\n\
+        -2\n\
+        3 0\n\
+        -1 0\n";
+        let v = |i| Var::new(i);
+        assert_eq!(
+            parse(cnf).unwrap(),
+            Dimacs::Cnf {
+                n_vars: 3,
+                clauses: vec![
+                    vec![v(0).pos_lit(), v(1).neg_lit(), v(2).pos_lit()],
+                    vec![v(0).neg_lit()]
+                ]
+            }
+        );
+        let wcnf = "p wcnf 2 2 10\n\
+        10\n\
+        1 -2\n\
+        0 4 2 0\n";
+        assert_eq!(
+            parse(wcnf).unwrap(),
+            Dimacs::Wcnf {
+                n_vars: 2,
+                hard_weight: Some(10),
+                clauses: vec![
+                    (vec![v(0).pos_lit(), v(1).neg_lit()], 10),
+                    (vec![v(1).pos_lit()], 4)
+                ]
+            }
+        );
+        assert!(matches!(
+            parse("p cnf 2 1\n1 2\n"),
+            Err(DimacsError::UnexpectedEof)
+        ));
+    }
+
+    #[test]
+    fn streaming_clauses() {
+        let wcnf = "c streamed\np wcnf 2 3 5\n5 1 2 0\n1 -1 0 2 -2 0\n";
+        let mut clauses = DimacsClauses::new(wcnf.as_bytes()).unwrap();
+        assert_eq!(clauses.n_vars(), 2);
+        assert_eq!(clauses.n_clauses(), 3);
+        let v = |i| Var::new(i);
+        assert_eq!(
+            clauses.next().unwrap().unwrap(),
+            ClauseItem {
+                lits: vec![v(0).pos_lit(), v(1).pos_lit()],
+                weight: Some(5)
+            }
+        );
+        assert_eq!(clauses.next().unwrap().unwrap().weight, Some(1));
+        assert_eq!(clauses.next().unwrap().unwrap().lits, vec![v(1).neg_lit()]);
+        assert!(clauses.next().is_none());
+    }
+
+    #[test]
+    fn write_roundtrip() {
+        let v = |i| Var::new(i);
+        let cnf = Dimacs::Cnf {
+            n_vars: 3,
+            clauses: vec![
+                vec![v(0).pos_lit(), v(2).neg_lit()],
+                vec![],
+                vec![v(1).neg_lit()],
+            ],
+        };
+        let wcnf = Dimacs::Wcnf {
+            n_vars: 2,
+            clauses: vec![
+                (vec![v(0).pos_lit(), v(1).pos_lit()], 7),
+                (vec![v(1).neg_lit()], 2),
+            ],
+            hard_weight: Some(7),
+        };
+        for dimacs in &[cnf, wcnf] {
+            let mut out = vec![];
+            write_dimacs(dimacs, &mut out).unwrap();
+            assert_eq!(&parse(std::str::from_utf8(&out).unwrap()).unwrap(), dimacs);
+        }
+    }
+
+    #[test]
+    fn display() {
+        let v = |i| Var::new(i);
+        let cnf = Dimacs::Cnf {
+            n_vars: 2,
+            clauses: vec![vec![v(0).pos_lit(), v(1).neg_lit()], vec![v(1).pos_lit()]],
+        };
+        assert_eq!(cnf.to_string(), "p cnf 2 2\n1 -2 0\n2 0\n");
+        let wcnf = Dimacs::Wcnf {
+            n_vars: 1,
+            clauses: vec![(vec![v(0).neg_lit()], 3)],
+            hard_weight: Some(10),
+        };
+        assert_eq!(wcnf.to_string(), "p wcnf 1 1 10\n3 -1 0\n");
+    }
+
+    #[test]
+    fn wcnf_cost() {
+        let wcnf = parse("p wcnf 2 4 100\n100 1 2 0\n5 -1 0\n7 -2 0\n3 1 0\n").unwrap();
+        assert_eq!(wcnf.cost(&[true, false]), Some(5));
+        assert_eq!(wcnf.cost(&[true, true]), Some(12));
+        assert_eq!(wcnf.cost(&[false, true]), Some(10));
+        assert_eq!(wcnf.cost(&[false, false]), None);
+        let big = Dimacs::Wcnf {
+            n_vars: 1,
+            clauses: vec![(vec![], u64::MAX), (vec![], u64::MAX)],
+            hard_weight: None,
+        };
+        assert_eq!(big.cost(&[]), Some(2 * u128::from(u64::MAX)));
+    }
+
+    #[test]
+    fn accessors() {
+        let cnf = parse("p cnf 3 2\n1 2 0\n-3 0\n").unwrap();
+        assert_eq!((cnf.n_vars(), cnf.n_clauses()), (3, 2));
+        let wcnf = parse("p wcnf 4 1\n2 1 0\n").unwrap();
+        assert_eq!((wcnf.n_vars(), wcnf.n_clauses()), (4, 1));
+    }
+
+    #[test]
+    fn iter_clauses() {
+        let lits = |d: &Dimacs| d.iter_clauses().map(|c| c.len()).collect::<Vec<_>>();
+        assert_eq!(
+            lits(&parse("p cnf 3 2\n1 2 0\n-3 0\n").unwrap()),
+            vec![2, 1]
+        );
+        assert_eq!(
+            lits(&parse("p wcnf 2 2\n5 1 2 0\n1 0\n").unwrap()),
+            vec![2, 0]
+        );
+    }
+
+    #[test]
+    fn wcnf_to_cnf() {
+        let wcnf = parse("p wcnf 2 3 9\n9 1 2 0\n3 -1 0\n9 -2 0\n").unwrap();
+        assert_eq!(
+            wcnf.to_cnf(true),
+            parse("p cnf 2 2\n1 2 0\n-2 0\n").unwrap()
+        );
+        assert_eq!(
+            wcnf.to_cnf(false),
+            parse("p cnf 2 3\n1 2 0\n-1 0\n-2 0\n").unwrap()
+        );
+        let no_hard = parse("p wcnf 1 1\n9 1 0\n").unwrap();
+        assert_eq!(no_hard.to_cnf(true).n_clauses(), 0);
+        assert_eq!(no_hard.to_cnf(false).n_clauses(), 1);
+        let over_weight = parse("p wcnf 2 2 9\n9 1 0\n12 2 0\n").unwrap();
+        assert_eq!(over_weight.to_cnf(true), parse("p cnf 2 1\n1 0\n").unwrap());
+    }
+
+    #[test]
+    fn hard_weight_is_exact() {
+        // Clauses heavier than the hard weight are soft everywhere.
+        let wcnf = parse("p wcnf 2 3 9\n9 1 0\n12 -2 0\n12 -1 2 0\n").unwrap();
+        assert_eq!(wcnf.to_cnf(true), parse("p cnf 2 1\n1 0\n").unwrap());
+        assert!(Solution::Sat(vec![true, false]).verify(&wcnf));
+        assert_eq!(wcnf.cost(&[true, false]), Some(12));
+        assert_eq!(wcnf.total_soft_weight(), 24);
+        assert_eq!(wcnf.stats().n_hard, 1);
+        assert!(wcnf.binary_implications().is_empty());
+        assert_eq!(wcnf.unit_propagate().unwrap().1, vec![Lit::from_dimacs(1)]);
+        assert!(matches!(
+            crate::two_sat::solve_2sat(&wcnf),
+            Solution::Best(_)
+        ));
+    }
+
+    #[test]
+    fn headerless_wcnf() {
+        let wcnf = "c This is a comment\n\
+        c This is another comment\n\
+        h 1 2 3 4 0\n\
+        1 -3 -5 6 7 0\n\
+        6 -1 -2 0\n\
+        4 1 6 -7 0\n";
+        let l = Lit::from_dimacs;
+        assert_eq!(
+            parse_headerless(wcnf).unwrap(),
+            Dimacs::Wcnf {
+                n_vars: 7,
+                clauses: vec![
+                    (vec![l(1), l(2), l(3), l(4)], 12),
+                    (vec![l(-3), l(-5), l(6), l(7)], 1),
+                    (vec![l(-1), l(-2)], 6),
+                    (vec![l(1), l(6), l(-7)], 4),
+                ],
+                hard_weight: Some(12),
+            }
+        );
+        assert!(matches!(
+            parse_headerless("h 1 0\nh 2\n"),
+            Err(DimacsError::UnexpectedEof)
+        ));
+
+        // The soft weights must sum to less than the largest weight, so that
+        // the hard weight exceeds every soft weight.
+        let max = u64::MAX;
+        let fits = format!("{} 1 0\n1 2 0\nh -1 0\n", max - 2);
+        let dimacs = parse_headerless(&fits).unwrap();
+        assert_eq!(dimacs.effective_hard_weight(), max);
+        assert_eq!(dimacs.total_soft_weight(), u128::from(max - 1));
+        for (s, line, weight) in &[
+            (format!("{} 1 0\n5 2 0\nh -1 0\n", max), 1, max),
+            (format!("{} 1 0\n1 2 0\n", max - 1), 2, 1),
+        ] {
+            match parse_headerless(s) {
+                Err(DimacsError::InvalidWeight { line: l, token }) => {
+                    assert_eq!((l, token), (*line, weight.to_string()))
+                }
+                r => panic!("unexpected {:?}", r),
+            }
+        }
+
+        // Without the option, input lacking a problem line is rejected rather
+        // than its leading literals taken as weights.
+        for s in &[wcnf, "1 -2 0\n2 0\n"] {
+            assert!(matches!(parse(s), Err(DimacsError::InvalidHeader { .. })));
+        }
+        assert!(matches!(
+            parse("c x\n1 -2 0\n"),
+            Err(DimacsError::InvalidHeader { line: 2 })
+        ));
+    }
+
+    #[cfg(feature = "gzip")]
+    #[test]
+    fn gzip_file() {
+        let path = std::env::temp_dir().join(format!("solhop-{}.cnf.gz", std::process::id()));
+        let mut encoder = flate2::write::GzEncoder::new(
+            std::fs::File::create(&path).unwrap(),
+            flate2::Compression::default(),
+        );
+        encoder.write_all(b"p cnf 2 1\n1 -2 0\n").unwrap();
+        encoder.finish().unwrap();
+        let dimacs = parse_dimacs_from_file(&path);
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(
+            dimacs.unwrap(),
+            Dimacs::Cnf {
+                n_vars: 2,
+                clauses: vec![vec![Lit::from_dimacs(1), Lit::from_dimacs(-2)]]
+            }
+        );
+    }
+
+    #[cfg(feature = "mmap")]
+    #[test]
+    fn mmap_file() {
+        let path = std::env::temp_dir().join(format!("solhop-{}-mmap.wcnf", std::process::id()));
+        std::fs::write(&path, "c mapped\np wcnf 2 2 5\n5 1 -2 0\n3 2\n0\n").unwrap();
+        let mapped = parse_dimacs_mmap(&path);
+        let read = parse_dimacs_from_file(&path);
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(mapped.unwrap(), read.unwrap());
+        assert!(matches!(
+            parse_dimacs_mmap(std::path::Path::new("does/not/exist.cnf")),
+            Err(DimacsError::Io(_))
+        ));
+    }
+
+    #[test]
+    fn strict_var_range() {
+        let cnf = "p cnf 2 2\n1 -2 0\n\n3 1 0\n";
+        assert!(parse(cnf).is_ok());
+        let strict = ParseOptions {
+            strict_var_range: true,
+            ..ParseOptions::default()
+        };
+        match parse_dimacs_with_options(&mut cnf.as_bytes(), strict) {
+            Err(DimacsError::VariableOutOfRange { line, var }) => {
+                assert_eq!(line, 4);
+                assert_eq!(var, Var::from_dimacs(3));
+            }
+            r => panic!("unexpected result {:?}", r),
+        }
+    }
+
+    #[test]
+    fn check_clause_count() {
+        let options = ParseOptions {
+            check_clause_count: true,
+            ..ParseOptions::default()
+        };
+        let parse = |s: &str| parse_dimacs_with_options(&mut s.as_bytes(), options);
+        assert!(parse("p cnf 2 2\n1 0\n2 0\n").is_ok());
+        assert!(matches!(
+            parse("p cnf 2 3\n1 0\n2 0\n"),
+            Err(DimacsError::ClauseCountMismatch {
+                declared: 3,
+                actual: 2
+            })
+        ));
+        assert!(matches!(
+            parse("p cnf 2 1\n1 0\n2 0\n-1 0\n"),
+            Err(DimacsError::ClauseCountMismatch {
+                declared: 1,
+                actual: 3
+            })
+        ));
+    }
+
+    #[test]
+    fn builder() {
+        let l = Lit::from_dimacs;
+        let mut cnf = DimacsBuilder::new_cnf();
+        cnf.add_clause(vec![l(1), l(-3)]).add_clause(vec![l(2)]);
+        assert_eq!(cnf.build(), parse("p cnf 3 2\n1 -3 0\n2 0\n").unwrap());
+
+        let mut wcnf = DimacsBuilder::new_wcnf(10);
+        wcnf.add_clause([1, 2]).add_soft_clause(3, vec![l(-4)]);
+        assert_eq!(
+            wcnf.build(),
+            parse("p wcnf 4 2 10\n10 1 2 0\n3 -4 0\n").unwrap()
+        );
+    }
+
+    #[test]
+    fn solution_output() {
+        let output = |sol: &Solution| {
+            let mut out = vec![];
+            write_solution(sol, &mut out).unwrap();
+            String::from_utf8(out).unwrap()
+        };
+        assert_eq!(
+            output(&Solution::Sat(vec![true, false, true])),
+            "s SATISFIABLE\nv 1 -2 3 0\n"
+        );
+        assert_eq!(output(&Solution::Unsat), "s UNSATISFIABLE\n");
+        assert_eq!(output(&Solution::Unknown), "s UNKNOWN\n");
+        assert_eq!(output(&Solution::Best(vec![true])), "s UNKNOWN\n");
+    }
+
+    #[test]
+    fn solution_input() {
+        let parse = |s: &str, n_vars| parse_solution_from_buf_reader(&mut s.as_bytes(), n_vars);
+        assert_eq!(
+            parse("c solver\ns SATISFIABLE\nv 1 -2\nv 3 0\n", 4).unwrap(),
+            Solution::Sat(vec![true, false, true, false])
+        );
+        assert_eq!(parse("s UNSATISFIABLE\n", 4).unwrap(), Solution::Unsat);
+        assert_eq!(parse("s UNKNOWN\n", 4).unwrap(), Solution::Unknown);
+        assert!(matches!(
+            parse("s SATISFIABLE\nv 1 5 0\n", 4),
+            Err(DimacsError::VariableOutOfRange { line: 2, .. })
+        ));
+        assert!(matches!(
+            parse("s MAYBE\n", 4),
+            Err(DimacsError::InvalidSolution { line: 1 })
+        ));
+        assert!(matches!(
+            parse("v 1 0\n", 4),
+            Err(DimacsError::UnexpectedEof)
+        ));
+        let sol = Solution::Sat(vec![false, true]);
+        let mut out = vec![];
+        write_solution(&sol, &mut out).unwrap();
+        assert_eq!(parse(std::str::from_utf8(&out).unwrap(), 2).unwrap(), sol);
+    }
+
+    #[test]
+    fn missing_file() {
+        let err = parse_dimacs_from_file(std::path::Path::new("does/not/exist.cnf")).unwrap_err();
+        match err {
+            DimacsError::Io(e) => assert_eq!(e.kind(), io::ErrorKind::NotFound),
+            e => panic!("unexpected error {:?}", e),
+        }
+    }
+
+    #[test]
+    fn wcnf_weights() {
+        assert!(matches!(
+            parse("p wcnf 2 2 10\n10 1 2 0\n-3 -1 0\n"),
+            Err(DimacsError::InvalidWeight { line: 3, ref token }) if token == "-3"
+        ));
+        let dimacs = parse("p wcnf 2 2 10\n10 1 2 0\n0 -1 0\n").unwrap();
+        assert_eq!(
+            dimacs,
+            Dimacs::Wcnf {
+                n_vars: 2,
+                clauses: vec![
+                    (vec![Lit::from_dimacs(1), Lit::from_dimacs(2)], 10),
+                    (vec![Lit::from_dimacs(-1)], 0),
+                ],
+                hard_weight: Some(10),
+            }
+        );
+        assert_eq!(dimacs.cost(&[true, false]), Some(0));
+    }
+
+    #[test]
+    fn comments_roundtrip() {
+        let input = "c source: generator\nc\np cnf 2 2\n1 2 0\nc middle\n-1 0\nc end\n";
+        let (dimacs, comments) =
+            parse_dimacs_with_comments(&mut input.as_bytes(), ParseOptions::default()).unwrap();
+        assert_eq!(dimacs, parse(input).unwrap());
+        assert_eq!(comments, vec!["source: generator", "", "middle", "end"]);
+
+        let mut out = vec![];
+        write_dimacs_with_comments(&dimacs, &comments, &mut out).unwrap();
+        let out = std::str::from_utf8(&out).unwrap();
+        assert!(out.starts_with("c source: generator\nc\nc middle\nc end\np cnf 2 2\n"));
+        let (again, again_comments) =
+            parse_dimacs_with_comments(&mut out.as_bytes(), ParseOptions::default()).unwrap();
+        assert_eq!(again, dimacs);
+        assert_eq!(again_comments, comments);
+    }
+
+    #[test]
+    fn stats() {
+        let wcnf = parse("p wcnf 4 4 10\n10 1 2 3 0\n10 -4 0\n3 -1 0\n4 2 -3 0\n").unwrap();
+        assert_eq!(
+            wcnf.stats(),
+            DimacsStats {
+                n_vars: 4,
+                n_clauses: 4,
+                n_units: 2,
+                max_clause_len: 3,
+                avg_clause_len: 1.75,
+                n_hard: 2,
+                n_soft: 2,
+                total_soft_weight: 7,
+            }
+        );
+        let cnf = wcnf.to_cnf(false).stats();
+        assert_eq!((cnf.n_hard, cnf.n_soft, cnf.total_soft_weight), (4, 0, 0));
+        let empty = parse("p cnf 3 0\n").unwrap().stats();
+        assert_eq!((empty.max_clause_len, empty.avg_clause_len), (0, 0.0));
+    }
+
+    #[test]
+    fn normalize() {
+        let mut cnf = parse("p cnf 3 4\n1 -1 2 0\n1 2 1 0\n-3 0\n2 3 -2 2 0\n").unwrap();
+        assert_eq!(cnf.normalize(), 2);
+        assert_eq!(cnf, parse("p cnf 3 2\n1 2 0\n-3 0\n").unwrap());
+        assert_eq!(cnf.normalize(), 0);
+
+        let mut wcnf = parse("p wcnf 2 3 10\n10 1 -1 0\n3 2 -2 0\n4 1 1 2 0\n").unwrap();
+        assert_eq!(wcnf.normalize(), 2);
+        assert_eq!(wcnf, parse("p wcnf 2 1 10\n4 1 2 0\n").unwrap());
+    }
+
+    #[test]
+    fn equivalent() {
+        let a = parse("p cnf 3 3\n1 -2 0\n3 0\n1 -2 0\n").unwrap();
+        let b = parse("p cnf 3 3\n3 0\n-2 1 0\n1 -2 0\n").unwrap();
+        assert_ne!(a, b);
+        assert!(a.equivalent(&b));
+        assert!(!a.equivalent(&parse("p cnf 3 2\n3 0\n1 -2 0\n").unwrap()));
+        assert!(!a.equivalent(&parse("p cnf 4 3\n3 0\n-2 1 0\n1 -2 0\n").unwrap()));
+        assert!(a.equivalent(&a));
+
+        let a = parse("p wcnf 2 2 9\n9 1 2 0\n4 -1 0\n").unwrap();
+        assert!(a.equivalent(&parse("p wcnf 2 2 9\n4 -1 0\n9 2 1 0\n").unwrap()));
+        assert!(!a.equivalent(&parse("p wcnf 2 2 9\n4 1 2 0\n9 -1 0\n").unwrap()));
+        assert!(!a.equivalent(&parse("p wcnf 2 2 8\n9 1 2 0\n4 -1 0\n").unwrap()));
+        assert!(!a.equivalent(&a.to_cnf(false)));
+    }
+
+    #[test]
+    fn used_variables() {
+        let sparse = parse("p cnf 5 2\n1 -4 0\n4 1 0\n").unwrap();
+        let vars: Vec<_> = sparse.used_variables().into_iter().collect();
+        assert_eq!(vars, vec![Var::new(0), Var::new(3)]);
+        assert!(!sparse.is_dense());
+        assert!(parse("p wcnf 2 2\n1 -2 0\n3 1 0\n").unwrap().is_dense());
+        assert!(parse("p cnf 0 0\n").unwrap().is_dense());
+    }
+
+    #[test]
+    fn compact() {
+        let sparse = parse("p wcnf 6 3 10\n10 2 -5 0\n3 5 0\n4 -2 6 0\n").unwrap();
+        let (compacted, vars) = sparse.compact();
+        assert_eq!(
+            compacted,
+            parse("p wcnf 3 3 10\n10 1 -2 0\n3 2 0\n4 -1 3 0\n").unwrap()
+        );
+        assert_eq!(vars, vec![Var::new(1), Var::new(4), Var::new(5)]);
+        for bits in 0..8u32 {
+            let model: Vec<bool> = (0..3).map(|i| bits & (1 << i) != 0).collect();
+            let mut original = vec![false; sparse.n_vars()];
+            for (var, &value) in vars.iter().zip(&model) {
+                original[var.index()] = value;
+            }
+            assert_eq!(compacted.cost(&model), sparse.cost(&original));
+        }
+    }
+
+    #[test]
+    fn effective_hard_weight() {
+        assert_eq!(
+            parse("p wcnf 2 2 10\n10 1 0\n3 2 0\n")
+                .unwrap()
+                .effective_hard_weight(),
+            10
+        );
+        assert_eq!(
+            parse("p wcnf 2 2\n4 1 0\n3 2 0\n")
+                .unwrap()
+                .effective_hard_weight(),
+            8
+        );
+        let max = format!("p wcnf 1 2\n{} 1 0\n2 -1 0\n", u64::MAX - 1);
+        assert_eq!(parse(&max).unwrap().effective_hard_weight(), u64::MAX);
+        assert_eq!(
+            parse("p cnf 1 1\n1 0\n").unwrap().effective_hard_weight(),
+            1
+        );
+    }
+
+    #[test]
+    fn length_histogram() {
+        let dimacs = parse("p cnf 3 4\n1 0\n1 2 0\n-2 3 0\n1 2 3 0\n").unwrap();
+        let histogram: Vec<_> = dimacs.length_histogram().into_iter().collect();
+        assert_eq!(histogram, vec![(1, 1), (2, 2), (3, 1)]);
+        assert!(parse("p cnf 3 0\n").unwrap().length_histogram().is_empty());
+    }
+
+    #[test]
+    fn classification() {
+        let horn = parse("p cnf 3 3\n-1 -2 3 0\n1 0\n-3 -1 0\n").unwrap();
+        assert!(horn.is_horn());
+        assert!(!horn.is_dual_horn());
+        assert!(!horn.is_2sat());
+
+        let dual_horn = parse("p cnf 3 2\n1 2 -3 0\n3 1 0\n").unwrap();
+        assert!(!dual_horn.is_horn());
+        assert!(dual_horn.is_dual_horn());
+
+        let two_sat = parse("p wcnf 3 3\n1 1 2 0\n2 -1 -3 0\n3 0\n").unwrap();
+        assert!(two_sat.is_2sat());
+        assert!(!two_sat.is_horn());
+        assert!(!two_sat.is_dual_horn());
+
+        let empty = parse("p cnf 0 0\n").unwrap();
+        assert!(empty.is_horn() && empty.is_dual_horn() && empty.is_2sat());
+    }
+
+    #[test]
+    fn pure_literals() {
+        let dimacs = parse("p cnf 4 3\n1 -2 3 0\n-1 -2 0\n-3 1 -2 0\n").unwrap();
+        assert_eq!(dimacs.pure_literals(), vec![Lit::from_dimacs(-2)]);
+        let dimacs = parse("p cnf 3 2\n1 -3 0\n-3 1 5 0\n").unwrap();
+        assert_eq!(
+            dimacs.pure_literals(),
+            vec![
+                Lit::from_dimacs(1),
+                Lit::from_dimacs(-3),
+                Lit::from_dimacs(5)
+            ]
+        );
+    }
+
+    #[test]
+    fn unit_propagate() {
+        let lits =
+            |ints: &[i32]| -> Vec<Lit> { ints.iter().map(|&i| Lit::from_dimacs(i)).collect() };
+        let chain = parse("p cnf 5 5\n1 0\n-1 2 0\n-2 -3 0\n3 4 5 0\n-4 5 2 0\n").unwrap();
+        let (simplified, implied) = chain.unit_propagate().unwrap();
+        assert_eq!(implied, lits(&[1, 2, -3]));
+        assert_eq!(simplified, parse("p cnf 5 1\n4 5 0\n").unwrap());
+
+        let conflict = parse("p cnf 2 4\n1 0\n-1 2 0\n-2 -1 0\n2 1 0\n").unwrap();
+        assert_eq!(conflict.unit_propagate(), None);
+        assert_eq!(parse("p cnf 1 1\n0\n").unwrap().unit_propagate(), None);
+
+        let wcnf = parse("p wcnf 3 4 10\n10 1 0\n3 -2 0\n4 -1 0\n10 -1 3 2 0\n").unwrap();
+        let (simplified, implied) = wcnf.unit_propagate().unwrap();
+        assert_eq!(implied, lits(&[1]));
+        assert_eq!(
+            simplified,
+            Dimacs::Wcnf {
+                n_vars: 3,
+                clauses: vec![(lits(&[-2]), 3), (vec![], 4), (lits(&[3, 2]), 10)],
+                hard_weight: Some(10),
+            }
+        );
+        assert_eq!(simplified.cost(&[false, false, true]), Some(4));
+        assert_eq!(wcnf.cost(&[true, false, true]), Some(4));
+    }
+
+    #[test]
+    fn merge() {
+        let a = parse("p cnf 2 1\n1 -2 0\n").unwrap();
+        let b = parse("p cnf 3 2\n3 0\n-1 0\n").unwrap();
+        assert_eq!(
+            a.merge(&b).unwrap(),
+            parse("p cnf 3 3\n1 -2 0\n3 0\n-1 0\n").unwrap()
+        );
+        let (merged, offset) = a.merge_disjoint(&b).unwrap();
+        assert_eq!(offset, 2);
+        assert_eq!(merged, parse("p cnf 5 3\n1 -2 0\n5 0\n-3 0\n").unwrap());
+
+        let a = parse("p wcnf 1 1 5\n5 1 0\n").unwrap();
+        let b = parse("p wcnf 2 1 5\n2 -2 0\n").unwrap();
+        assert_eq!(
+            a.merge(&b).unwrap(),
+            parse("p wcnf 2 2 5\n5 1 0\n2 -2 0\n").unwrap()
+        );
+        assert_eq!(
+            a.merge_disjoint(&b).unwrap().0,
+            parse("p wcnf 3 2 5\n5 1 0\n2 -3 0\n").unwrap()
+        );
+    }
+
+    #[test]
+    fn merge_mismatched() {
+        let cnf = parse("p cnf 1 1\n1 0\n").unwrap();
+        let wcnf = parse("p wcnf 1 1 5\n1 1 0\n").unwrap();
+        assert_eq!(cnf.merge(&wcnf), None);
+        assert_eq!(wcnf.merge_disjoint(&cnf), None);
+        let other_hard = parse("p wcnf 1 1 6\n1 1 0\n").unwrap();
+        assert_eq!(wcnf.merge(&other_hard), None);
+    }
+
+    #[test]
+    fn from_str() {
+        let s = "c comment\np cnf 2 1\n1 -2 0\n";
+        assert_eq!(
+            parse_dimacs_from_str(s).unwrap(),
+            parse_dimacs_from_buf_reader(&mut std::io::BufReader::new(s.as_bytes())).unwrap()
+        );
+        assert!(matches!(
+            parse_dimacs_from_str("p cnf x 1\n"),
+            Err(DimacsError::InvalidHeader { line: 1 })
+        ));
+    }
+
+    #[test]
+    fn reusable_parser() {
+        let parser = DimacsParser::new();
+        for s in &["p cnf 2 1\n1 -2 0\n", "p wcnf 1 1 4\n4 1 0\n"] {
+            assert_eq!(parser.parse(s.as_bytes()).unwrap(), parse(s).unwrap());
+        }
+        // The buffers are kept for the next parse.
+        let long = format!(
+            "p cnf 40 1\n{} 0\n",
+            (1..=40)
+                .map(|i| i.to_string())
+                .collect::<Vec<_>>()
+                .join(" ")
+        );
+        assert_eq!(
+            parser.parse(long.as_bytes()).unwrap(),
+            parse(&long).unwrap()
+        );
+        let capacities = |parser: &DimacsParser| {
+            (
+                parser.line.borrow().capacity(),
+                parser.lits.borrow().capacity(),
+            )
+        };
+        let (line, lits) = capacities(&parser);
+        assert!(line >= long.len() - 11 && lits >= 40);
+        assert!(parser.parse("p cnf 1 1\n1 0\n".as_bytes()).is_ok());
+        assert!(parser.parse("p cnf x 1\n".as_bytes()).is_err());
+        assert_eq!(capacities(&parser), (line, lits));
+
+        // A visitor may parse with the parser it is driven by.
+        struct Nested<'a>(&'a DimacsParser, usize);
+        impl DimacsVisitor for Nested<'_> {
+            fn header(&mut self, _: DimacsFormat, _: usize, _: usize, _: Option<u64>) {}
+            fn clause(&mut self, _: &[Lit], _: Option<u64>) {
+                self.1 += self
+                    .0
+                    .parse("p cnf 1 1\n1 0\n".as_bytes())
+                    .unwrap()
+                    .n_clauses();
+            }
+        }
+        let mut nested = Nested(&parser, 0);
+        parser
+            .parse_with("p cnf 1 2\n1 0\n-1 0\n".as_bytes(), &mut nested)
+            .unwrap();
+        assert_eq!(nested.1, 2);
+        assert_eq!(capacities(&parser), (line, lits));
+
+        let strict = DimacsParser::with_options(ParseOptions {
+            strict_var_range: true,
+            ..ParseOptions::default()
+        });
+        assert!(strict.parse("p cnf 1 1\n2 0\n".as_bytes()).is_err());
+        assert!(parser.parse("p cnf 1 1\n2 0\n".as_bytes()).is_ok());
+    }
+
+    #[test]
+    fn empty_clause() {
+        let dimacs = parse("p cnf 2 2\n1 -2 0\n0\n").unwrap();
+        assert!(dimacs.has_empty_clause());
+        assert_eq!(
+            dimacs.iter_clauses().nth(1),
+            Some(&Clause::empty().lits[..])
+        );
+        assert!(!parse("p cnf 2 1\n1 -2 0\n").unwrap().has_empty_clause());
+    }
+
+    #[test]
+    fn header_only() {
+        let mut input = "c x\n\np wcnf 3 2 7\n7 1 0\n".as_bytes();
+        assert_eq!(
+            parse_dimacs_header(&mut input).unwrap(),
+            DimacsHeader {
+                format: DimacsFormat::Wcnf,
+                n_vars: 3,
+                n_clauses: 2,
+                hard_weight: Some(7),
+            }
+        );
+        assert_eq!(input, b"7 1 0\n");
+        let header = parse_dimacs_header(&mut "p cnf 4 5\n".as_bytes()).unwrap();
+        assert_eq!(
+            (header.format, header.hard_weight),
+            (DimacsFormat::Cnf, None)
+        );
+        assert!(matches!(
+            parse_dimacs_header(&mut "1 1 0\n".as_bytes()),
+            Err(DimacsError::InvalidHeader { line: 1 })
+        ));
+    }
+
+    #[test]
+    fn hard_weight_roundtrip() {
+        for &(hard_weight, header) in &[(Some(9), "p wcnf 2 1 9\n"), (None, "p wcnf 2 1\n")] {
+            let wcnf = Dimacs::Wcnf {
+                n_vars: 2,
+                clauses: vec![(vec![Lit::from_dimacs(1), Lit::from_dimacs(-2)], 9)],
+                hard_weight,
+            };
+            let mut out = vec![];
+            write_dimacs(&wcnf, &mut out).unwrap();
+            let out = String::from_utf8(out).unwrap();
+            assert!(out.starts_with(header), "{:?}", out);
+            assert_eq!(parse(&out).unwrap(), wcnf);
+        }
+    }
+
+    #[test]
+    fn satlib_trailer() {
+        let satlib = "p cnf 3 2\n1 -2 0\n2 3 0\n%\n0\n\n";
+        assert_eq!(
+            parse(satlib).unwrap(),
+            parse("p cnf 3 2\n1 -2 0\n2 3 0\n").unwrap()
+        );
+        let strict = ParseOptions {
+            check_clause_count: true,
+            ..ParseOptions::default()
+        };
+        assert!(parse_dimacs_with_options(&mut satlib.as_bytes(), strict).is_ok());
+        assert!(matches!(
+            parse_dimacs_with_options(&mut "p cnf 3 2\n1 0\n%\n2 0\n".as_bytes(), strict),
+            Err(DimacsError::ClauseCountMismatch {
+                declared: 2,
+                actual: 1
+            })
+        ));
+    }
+
+    #[test]
+    fn windows_input() {
+        let unix = "c generated\np wcnf 2 3 5\n5 1 2 0\n2 -1\n0\n1 -2 0\n";
+        let crlf = unix.replace('\n', "\r\n");
+        for input in &[
+            crlf.clone(),
+            format!("\u{feff}{}", unix),
+            format!("\u{feff}{}", crlf),
+        ] {
+            assert_eq!(parse(input).unwrap(), parse(unix).unwrap());
+            let (_, comments) =
+                parse_dimacs_with_comments(&mut input.as_bytes(), ParseOptions::default()).unwrap();
+            assert_eq!(comments, vec!["generated"]);
+        }
+        assert_eq!(
+            parse("\u{feff}p cnf 1 1\r\n1 0\r\n").unwrap(),
+            parse("p cnf 1 1\n1 0\n").unwrap()
+        );
+    }
+
+    #[test]
+    fn clause_size_hint() {
+        let input = "p cnf 2 3\n1 0\n2 0\n-1 -2 0\n";
+        let mut clauses = DimacsClauses::new(input.as_bytes()).unwrap();
+        assert_eq!(clauses.size_hint(), (0, Some(3)));
+        clauses.next();
+        assert_eq!(clauses.size_hint(), (0, Some(2)));
+        assert_eq!(clauses.by_ref().count(), 2);
+        assert_eq!(clauses.size_hint(), (0, Some(0)));
+
+        let strict = ParseOptions {
+            check_clause_count: true,
+            ..ParseOptions::default()
+        };
+        let clauses = DimacsClauses::with_options(input.as_bytes(), strict).unwrap();
+        assert_eq!(clauses.size_hint(), (0, None));
+
+        // A bogus clause count is no reason to fail.
+        let huge = parse("p cnf 2 18446744073709551615\n1 0\n").unwrap();
+        assert_eq!(huge.n_clauses(), 1);
+    }
+
+    #[test]
+    fn clause_options() {
+        let input = "p wcnf 3 4 9\n9 3 -1 3 0\n2 1 2 -1 0\n4 -2 -2 0\n9 2 1 0\n";
+        let with = |options| parse_dimacs_with_options(&mut input.as_bytes(), options).unwrap();
+        assert_eq!(with(ParseOptions::default()), parse(input).unwrap());
+
+        let canonical = ParseOptions {
+            canonicalize_clauses: true,
+            ..ParseOptions::default()
+        };
+        let expected = "p wcnf 3 4 9\n9 -1 3 0\n2 1 -1 2 0\n4 -2 0\n9 1 2 0\n";
+        assert_eq!(with(canonical), parse(expected).unwrap());
+
+        let drop = ParseOptions {
+            drop_tautologies: true,
+            check_clause_count: true,
+            ..ParseOptions::default()
+        };
+        let expected = "p wcnf 3 3 9\n9 3 -1 3 0\n4 -2 -2 0\n9 2 1 0\n";
+        assert_eq!(with(drop), parse(expected).unwrap());
+
+        let both = ParseOptions {
+            canonicalize_clauses: true,
+            ..drop
+        };
+        let expected = "p wcnf 3 3 9\n9 -1 3 0\n4 -2 0\n9 1 2 0\n";
+        assert_eq!(with(both), parse(expected).unwrap());
+    }
+
+    #[test]
+    fn trailing_data() {
+        let options = ParseOptions {
+            check_trailing_data: true,
+            ..ParseOptions::default()
+        };
+        let with = |s: &str| parse_dimacs_with_options(&mut s.as_bytes(), options);
+        let cnf = "p cnf 2 2\n1 -2 0\n2 0\nc done\n\n";
+        assert_eq!(with(cnf).unwrap(), parse(cnf).unwrap());
+        assert!(with("p cnf 2 1\n1 0\n%\n0\n").is_ok());
+
+        let extra = "p cnf 2 2\n1 -2 0\n2 0\nc more\n-1 0\n";
+        assert_eq!(parse(extra).unwrap().n_clauses(), 2);
+        assert!(matches!(
+            with(extra),
+            Err(DimacsError::TrailingData { line: 5 })
+        ));
+        assert!(matches!(
+            with("p wcnf 2 1 5\n5 1 0 3 2 0\n"),
+            Err(DimacsError::TrailingData { line: 2 })
+        ));
+        assert!(matches!(
+            with("p cnf 2 0\n1 0\n"),
+            Err(DimacsError::TrailingData { line: 2 })
+        ));
+
+        let mut clauses = DimacsClauses::with_options(extra.as_bytes(), options).unwrap();
+        assert!(clauses.next().unwrap().is_ok());
+        assert!(clauses.next().unwrap().is_ok());
+        assert!(clauses.next().unwrap().is_err());
+        assert!(clauses.next().is_none());
+    }
+
+    #[test]
+    fn visitor() {
+        #[derive(Default)]
+        struct Counter {
+            header: Option<(DimacsFormat, usize, usize, Option<u64>)>,
+            n_lits: usize,
+            weights: Vec<Option<u64>>,
+        }
+
+        impl DimacsVisitor for Counter {
+            fn header(
+                &mut self,
+                format: DimacsFormat,
+                n_vars: usize,
+                n_clauses: usize,
+                hard_weight: Option<u64>,
+            ) {
+                assert!(self.header.is_none());
+                self.header = Some((format, n_vars, n_clauses, hard_weight));
+            }
+
+            fn clause(&mut self, lits: &[Lit], weight: Option<u64>) {
+                self.n_lits += lits.len();
+                self.weights.push(weight);
+            }
+        }
+
+        let visit = |s: &str| {
+            let mut counter = Counter::default();
+            parse_dimacs_with(s.as_bytes(), &mut counter).map(|()| counter)
+        };
+        let cnf = visit("c x\np cnf 3 2\n1 -2 0\n3 -1\n2 0\n").unwrap();
+        assert_eq!(cnf.header, Some((DimacsFormat::Cnf, 3, 2, None)));
+        assert_eq!((cnf.n_lits, cnf.weights), (5, vec![None, None]));
+
+        let wcnf = visit("p wcnf 2 2 9\n9 1 2 0\n3 -1 0\n").unwrap();
+        assert_eq!(wcnf.header, Some((DimacsFormat::Wcnf, 2, 2, Some(9))));
+        assert_eq!(wcnf.weights, vec![Some(9), Some(3)]);
+
+        let mut headerless = Counter::default();
+        DimacsParser::with_options(HEADERLESS)
+            .parse_with("h 1 2 0\n4 -1 0\n".as_bytes(), &mut headerless)
+            .unwrap();
+        assert_eq!(headerless.header, Some((DimacsFormat::Wcnf, 0, 0, None)));
+        assert_eq!(headerless.weights, vec![None, Some(4)]);
+
+        assert!(visit("p cnf 1 1\n1 x 0\n").is_err());
+
+        let strict = DimacsParser::with_options(ParseOptions {
+            drop_tautologies: true,
+            ..ParseOptions::default()
+        });
+        let mut counter = Counter::default();
+        strict
+            .parse_with("p cnf 2 2\n1 -1 0\n2 0\n".as_bytes(), &mut counter)
+            .unwrap();
+        assert_eq!((counter.n_lits, counter.weights.len()), (1, 1));
+    }
+
+    #[test]
+    fn literal_counts() {
+        let dimacs = parse("p cnf 3 3\n1 -2 0\n1 1 2 -1 0\n-2 0\n").unwrap();
+        assert_eq!(dimacs.literal_counts(), vec![2, 1, 1, 2]);
+        let scores = dimacs.jeroslow_wang_scores();
+        assert_eq!(scores, vec![0.25 + 0.0625, 0.0625, 0.0625, 0.25 + 0.5]);
+
+        let dimacs = parse("p wcnf 1 2 5\n5 1 0\n2 -3 0\n").unwrap();
+        assert_eq!(dimacs.literal_counts(), vec![1, 0, 0, 0, 0, 1]);
+        assert_eq!(dimacs.jeroslow_wang_scores()[5], 0.5);
+        assert!(parse("p cnf 0 0\n").unwrap().literal_counts().is_empty());
+    }
+
+    #[test]
+    fn huge_declared_var_count() {
+        // Every helper must cope with a header declaring more variables than
+        // could be allocated, see `n_vars_covering`.
+        let huge = parse_dimacs_bytes(b"p cnf 9223372036854775808 2\n1 0\n-1 2 0\n").unwrap();
+        let lit = Lit::from_dimacs;
+        assert_eq!(huge.literal_counts(), vec![1, 1, 1, 0]);
+        assert_eq!(huge.jeroslow_wang_scores(), vec![0.5, 0.25, 0.25, 0.0]);
+        assert_eq!(huge.pure_literals(), vec![lit(2)]);
+        assert!(!huge.is_dense());
+        assert_eq!(huge.used_variables().len(), 2);
+        assert_eq!(huge.compact().0.n_vars(), 2);
+        assert_eq!(huge.unit_propagate().unwrap().1, vec![lit(1), lit(2)]);
+        assert_eq!(huge.binary_implications().len(), 2);
+        assert_eq!(huge.stats().n_vars, huge.n_vars());
+        assert_eq!(huge.cost(&[true, true]), Some(0));
+        assert!(huge.equivalent(&huge.clone()));
+        assert!(huge
+            .to_string()
+            .starts_with("p cnf 9223372036854775808 2\n"));
+        let occs = OccurrenceLists::from_dimacs(&huge);
+        assert_eq!(
+            (&occs[lit(-1)][..], &occs[lit(2)][..]),
+            (&[1][..], &[1][..])
+        );
+        assert!(occs[lit(5)].is_empty());
+        assert_eq!(
+            crate::clause_db::ClauseDb::from_dimacs(&huge).to_dimacs(),
+            huge
+        );
+        assert_eq!(huge.merge(&huge).unwrap().n_clauses(), 4);
+        assert_eq!(huge.merge_disjoint(&huge), None);
+        assert_eq!(crate::two_sat::solve_2sat(&huge), Solution::Unknown);
+        assert!(!Solution::Sat(vec![true, true]).verify(&huge));
+    }
+
+    #[test]
+    fn binary_implications() {
+        let lit = Lit::from_dimacs;
+        let dimacs = parse("p cnf 3 4\n1 -2 0\n3 0\n-1 2 3 0\n2 3 0\n").unwrap();
+        let implications = dimacs.binary_implications();
+        assert_eq!(implications.len(), 4);
+        for &(a, b) in &[(-1, -2), (2, 1), (-2, 3), (-3, 2)] {
+            assert!(implications.contains(&(lit(a), lit(b))), "{} => {}", a, b);
+        }
+
+        let wcnf = parse("p wcnf 2 2 5\n5 1 2 0\n3 -1 -2 0\n").unwrap();
+        assert_eq!(
+            wcnf.binary_implications(),
+            vec![(lit(-1), lit(2)), (lit(-2), lit(1))]
+        );
+    }
+
+    #[test]
+    fn untrusted_bytes() {
+        let cases: &[&[u8]] = &[
+            b"p cnf 18446744073709551616 1\n1 0\n",
+            b"p cnf 1 1\n-2147483648 0\n",
+            b"p cnf 1 1\n2147483648 0\n",
+            b"p wcnf 1 1 5\n18446744073709551616 1 0\n",
+            b"p cnf 1 1\n1 \xff 0\n",
+            b"\xef\xbb",
+            b"p cnf \xd9\xa3 1\n",
+            b"p wcnf 1",
+            b"h",
+            b"%",
+        ];
+        for input in cases {
+            assert!(parse_dimacs_bytes(input).is_err(), "{:?}", input);
+        }
+        let huge_count = b"p cnf 2147483647 18446744073709551615\n-2147483647 0\n";
+        match parse_dimacs_bytes(huge_count).unwrap() {
+            // The declared count reserves no more than the input can hold.
+            Dimacs::Cnf { clauses, .. } => {
+                assert_eq!(clauses.len(), 1);
+                assert!(clauses.capacity() <= huge_count.len() / 2 + 1);
+            }
+            d => panic!("unexpected {:?}", d),
+        }
+
+        // Random edits of valid input must not panic.
+        let seeds: &[&[u8]] = &[
+            b"c x\np cnf 3 2\n1 -2 0\n3 0\n",
+            b"p wcnf 2 2 9\n9 1 2 0\n3 -1 0\n",
+            b"h 1 2 0\n4 -1 0\n",
+        ];
+        let alphabet = b"0123456789- \n\rpcwnfh%\xff";
+        let mut seed = 1u32;
+        let mut next = |n: usize| {
+            seed = seed.wrapping_mul(1_103_515_245).wrapping_add(12345);
+            (seed >> 16) as usize % n
+        };
+        for _ in 0..2000 {
+            let mut input = seeds[next(seeds.len())].to_vec();
+            for _ in 0..1 + next(4) {
+                let i = next(input.len() + 1);
+                let b = alphabet[next(alphabet.len())];
+                match next(3) {
+                    0 => input.insert(i, b),
+                    1 if i < input.len() => input[i] = b,
+                    _ if i < input.len() => {
+                        input.remove(i);
+                    }
+                    _ => {}
+                }
+            }
+            let _ = parse_dimacs_bytes(&input);
+        }
+    }
+
+    #[test]
+    fn total_soft_weight() {
+        let big = u64::MAX - 1;
+        let wcnf = parse(&format!("p wcnf 2 3\n{} 1 0\n{} 2 0\n3 -1 0\n", big, big)).unwrap();
+        assert_eq!(wcnf.total_soft_weight(), 2 * u128::from(big) + 3);
+        assert_eq!(wcnf.stats().total_soft_weight, wcnf.total_soft_weight());
+        assert_eq!(wcnf.effective_hard_weight(), u64::MAX);
+
+        let hard = parse("p wcnf 2 3 10\n10 1 0\n4 2 0\n3 -1 0\n").unwrap();
+        assert_eq!(hard.total_soft_weight(), 7);
+        assert_eq!(parse("p cnf 1 1\n1 0\n").unwrap().total_soft_weight(), 0);
+    }
+
+    #[test]
+    fn display_large_variable() {
+        let big = Var::from_index(i32::MAX as usize).pos_lit();
+        let dimacs = Dimacs::Cnf {
+            n_vars: i32::MAX as usize + 1,
+            clauses: vec![vec![!big]],
+        };
+        assert_eq!(dimacs.to_string(), "p cnf 2147483648 1\n-2147483648 0\n");
     }
 }
@@ -0,0 +1,328 @@
+use super::{
+    parse_dimacs_with_options, parse_lit, ClauseItem, Dimacs, DimacsClauses, DimacsError,
+    ParseOptions,
+};
+#[cfg(feature = "rayon")]
+use rayon::prelude::*;
+
+/// Chunks are at least this large, so small inputs are not split needlessly.
+#[cfg(feature = "rayon")]
+const MIN_CHUNK_SIZE: usize = 1 << 20;
+
+/// Parse dimacs from a byte slice, splitting the clauses over threads.
+///
+/// The header is read first, then the input is split into chunks at line
+/// boundaries which are parsed in parallel and reassembled in order. Clauses
+/// may span chunks. The result is exactly the one of
+/// [`parse_dimacs_with_options`]: if a chunk contains anything unexpected,
+/// such as an error or the headerless wcnf format, the input is parsed again
+/// sequentially to report it.
+#[cfg(feature = "rayon")]
+pub fn parse_dimacs_parallel(input: &[u8], options: ParseOptions) -> Result<Dimacs, DimacsError> {
+    parse_slice(input, options)
+}
+
+/// Parse dimacs from a byte slice, scanning it in place instead of copying
+/// its lines. With the `rayon` feature the input is parsed in parallel.
+pub(crate) fn parse_slice(input: &[u8], options: ParseOptions) -> Result<Dimacs, DimacsError> {
+    #[cfg(feature = "rayon")]
+    let chunk_size = (input.len() / (4 * rayon::current_num_threads())).max(MIN_CHUNK_SIZE);
+    #[cfg(not(feature = "rayon"))]
+    let chunk_size = usize::MAX;
+    parse_chunked(input, options, chunk_size)
+}
+
+/// Applies `f` to every item, in parallel with the `rayon` feature.
+fn map_chunks<I, T, F>(items: &[I], f: F) -> Vec<T>
+where
+    I: Sync,
+    T: Send,
+    F: Fn(&I) -> T + Sync + Send,
+{
+    #[cfg(feature = "rayon")]
+    let mapped = items.par_iter().map(f).collect();
+    #[cfg(not(feature = "rayon"))]
+    let mapped = items.iter().map(f).collect();
+    mapped
+}
+
+fn parse_chunked(
+    input: &[u8],
+    options: ParseOptions,
+    chunk_size: usize,
+) -> Result<Dimacs, DimacsError> {
+    match try_parse_chunked(input, options, chunk_size) {
+        Some(dimacs) => Ok(dimacs),
+        None => parse_dimacs_with_options(&mut &input[..], options),
+    }
+}
+
+/// A chunk parsed into clauses. The first clause continues the clause left
+/// open by the previous chunk, if any.
+struct ParsedChunk {
+    clauses: Vec<ClauseItem>,
+    /// The last clause is not terminated in this chunk.
+    open: bool,
+}
+
+/// Returns None where the sequential parser might behave differently.
+fn try_parse_chunked(input: &[u8], options: ParseOptions, chunk_size: usize) -> Option<Dimacs> {
+    let input = input.strip_prefix("\u{feff}".as_bytes()).unwrap_or(input);
+    let mut header_end = 0;
+    for line in input.split_inclusive(|&b| b == b'\n') {
+        header_end += line.len();
+        let line = std::str::from_utf8(line).ok()?.trim_start();
+        if !line.is_empty() && !line.starts_with('c') {
+            break;
+        }
+    }
+    let header = DimacsClauses::open(&input[..header_end], options).ok()?;
+    if header.headerless {
+        return None;
+    }
+    let (n_vars, is_wcnf) = (header.n_vars(), header.is_weighted());
+
+    // The `%` end marker is left to the sequential parser.
+    let chunks = map_chunks(&split_lines(&input[header_end..], chunk_size), |chunk| {
+        std::str::from_utf8(chunk)
+            .ok()
+            .filter(|chunk| !chunk.contains('%'))
+    })
+    .into_iter()
+    .collect::<Option<Vec<_>>>()?;
+    // Only the first token of a wcnf clause is a weight, so each chunk needs
+    // to know whether it starts inside a clause.
+    let starts_open = if is_wcnf {
+        let ends_open = map_chunks(&chunks, |chunk| wcnf_ends_open(chunk));
+        let mut open = false;
+        let mut starts_open = Vec::with_capacity(ends_open.len());
+        for ends_open in ends_open {
+            starts_open.push(open);
+            open = ends_open[open as usize];
+        }
+        starts_open
+    } else {
+        vec![false; chunks.len()]
+    };
+    let strict = options.strict_var_range;
+    let chunks: Vec<_> = chunks.into_iter().zip(starts_open).collect();
+    let parsed = map_chunks(&chunks, |&(chunk, open)| {
+        parse_chunk(chunk, is_wcnf, n_vars, strict, open)
+    })
+    .into_iter()
+    .collect::<Option<Vec<_>>>()?;
+
+    let mut clauses = Vec::with_capacity(parsed.iter().map(|c| c.clauses.len()).sum());
+    let mut pending: Option<ClauseItem> = None;
+    for chunk in parsed {
+        let n = chunk.clauses.len();
+        for (i, mut clause) in chunk.clauses.into_iter().enumerate() {
+            if let Some(mut first) = pending.take() {
+                first.lits.append(&mut clause.lits);
+                clause = first;
+            }
+            if i + 1 == n && chunk.open {
+                pending = Some(clause);
+            } else {
+                clauses.push(clause);
+            }
+        }
+    }
+    let n_clauses = header.n_clauses();
+    if options.check_clause_count {
+        if pending.is_some() || clauses.len() != n_clauses {
+            return None;
+        }
+    } else if options.check_trailing_data && (pending.is_some() || clauses.len() > n_clauses) {
+        return None;
+    } else if clauses.len() >= n_clauses {
+        clauses.truncate(n_clauses);
+    } else if pending.is_some() {
+        return None;
+    }
+    clauses.retain_mut(|c| options.keep_clause(&mut c.lits));
+
+    Some(if is_wcnf {
+        Dimacs::Wcnf {
+            n_vars,
+            clauses: clauses
+                .into_iter()
+                .map(|c| (c.lits, c.weight.unwrap_or(0)))
+                .collect(),
+            hard_weight: header.hard_weight(),
+        }
+    } else {
+        Dimacs::Cnf {
+            n_vars,
+            clauses: clauses.into_iter().map(|c| c.lits).collect(),
+        }
+    })
+}
+
+/// Split `input` into chunks of about `chunk_size` bytes ending at newlines.
+fn split_lines(mut input: &[u8], chunk_size: usize) -> Vec<&[u8]> {
+    let mut chunks = vec![];
+    while !input.is_empty() {
+        let end = match input.get(chunk_size..) {
+            Some(rest) => rest
+                .iter()
+                .position(|&b| b == b'\n')
+                .map_or(input.len(), |i| chunk_size + i + 1),
+            None => input.len(),
+        };
+        let (chunk, rest) = input.split_at(end);
+        chunks.push(chunk);
+        input = rest;
+    }
+    chunks
+}
+
+/// Tokens of a chunk, skipping blank and comment lines like [`super::Tokens`].
+fn tokens(chunk: &str) -> impl Iterator<Item = &str> {
+    chunk
+        .split('\n')
+        .filter(|line| {
+            let line = line.trim_start();
+            !line.is_empty() && !line.starts_with('c')
+        })
+        .flat_map(str::split_ascii_whitespace)
+}
+
+/// Returns whether a wcnf chunk ends inside a clause, when starting outside
+/// and inside a clause respectively.
+fn wcnf_ends_open(chunk: &str) -> [bool; 2] {
+    let mut open = [false, true];
+    for token in tokens(chunk) {
+        let zero = token.parse::<i32>() == Ok(0);
+        for open in &mut open {
+            // The weight opens a clause, a zero literal closes it.
+            *open = !*open || !zero;
+        }
+    }
+    open
+}
+
+fn parse_chunk(
+    chunk: &str,
+    is_wcnf: bool,
+    n_vars: usize,
+    strict_var_range: bool,
+    open: bool,
+) -> Option<ParsedChunk> {
+    let new_clause = |weight| ClauseItem {
+        lits: vec![],
+        weight,
+    };
+    let mut clauses = vec![];
+    let mut current = if open { Some(new_clause(None)) } else { None };
+    for token in tokens(chunk) {
+        if current.is_none() && is_wcnf {
+            current = Some(new_clause(Some(token.parse().ok()?)));
+            continue;
+        }
+        let clause = current.get_or_insert_with(|| new_clause(None));
+        match parse_lit(token, 0).ok()? {
+            Some(lit) => {
+                if strict_var_range && lit.var_index() >= n_vars {
+                    return None;
+                }
+                clause.lits.push(lit);
+            }
+            None => clauses.extend(current.take()),
+        }
+    }
+    let open = current.is_some();
+    clauses.extend(current);
+    Some(ParsedChunk { clauses, open })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn matches_sequential() {
+        let inputs = [
+            "p cnf 3 3\n1 -2 0\n3\n-1\n 0\nc comment\n0\n",
+            "c head\n\np cnf 3 2\n1 2 3 -1 -2 -3 0 2 0\n",
+            "p cnf 2 2\r\n1 2 0\r\n-1 0\r\n",
+            "p cnf 2 1\n1 0\nnot a clause\n",
+            "p cnf 2 3\n1 0\n",
+            "p cnf 2 2\n1 0\n2\n",
+            "p cnf 2 2\n1 x 0\n2 0\n",
+            "p cnf 2 2\n1 3 0\n2 0\n",
+            "p cnf 2 1\n1 0\n2 0\n",
+            "p wcnf 3 4 10\n10 1 2\n-3 0 0 1 0 4\n-2\n0\n3 3 0\n",
+            "p wcnf 2 2\n1 1\n 0 2\n-1 -2 0\n",
+            "p wcnf 2 2 10\n-1 1 0\n1 2 0\n",
+            "p wcnf 2 1 10\n10 1 0\n3\n",
+            "1 1 2 0\nh -1 0\n",
+            "p dnf 1 1\n1 0\n",
+            "c only comments\n",
+            "p cnf 2 2\n1 0\n2 0\n%\n0\n",
+            "\u{feff}p cnf 2 2\r\n1 0\r\n2 0\r\n",
+            "\u{feff}c bom\r\np cnf 1 1\r\n1 0\r\n",
+            "p cnf 3 3\n3 -1\n3 0 2 1 -2\n0 -3 -3 0\n",
+            "",
+        ];
+        let all_options = [
+            ParseOptions::default(),
+            ParseOptions {
+                strict_var_range: true,
+                check_clause_count: true,
+                ..ParseOptions::default()
+            },
+            ParseOptions {
+                canonicalize_clauses: true,
+                drop_tautologies: true,
+                check_trailing_data: true,
+                headerless_wcnf: true,
+                ..ParseOptions::default()
+            },
+        ];
+        for input in &inputs {
+            for &options in &all_options {
+                let expected = format!(
+                    "{:?}",
+                    parse_dimacs_with_options(&mut input.as_bytes(), options)
+                );
+                for chunk_size in 1..input.len() + 2 {
+                    let actual =
+                        format!("{:?}", parse_chunked(input.as_bytes(), options, chunk_size));
+                    assert_eq!(actual, expected, "{:?} in chunks of {}", input, chunk_size);
+                }
+            }
+        }
+        // Valid input is parsed in chunks, not by falling back.
+        for &i in &[0, 1, 2, 9, 10] {
+            let input = inputs[i];
+            for chunk_size in 1..input.len() + 2 {
+                let chunked = try_parse_chunked(input.as_bytes(), all_options[1], chunk_size);
+                assert!(chunked.is_some(), "{:?} in chunks of {}", input, chunk_size);
+            }
+        }
+    }
+
+    #[test]
+    fn large_input() {
+        let mut input = String::from("p cnf 100 5000\n");
+        for i in 0..5000 {
+            input.push_str(&format!(
+                "{} -{} {}\n0\n",
+                i % 100 + 1,
+                (i * 7) % 100 + 1,
+                i % 3 + 1
+            ));
+        }
+        let expected =
+            parse_dimacs_with_options(&mut input.as_bytes(), ParseOptions::default()).unwrap();
+        assert_eq!(
+            parse_slice(input.as_bytes(), ParseOptions::default()).unwrap(),
+            expected
+        );
+        assert_eq!(
+            try_parse_chunked(input.as_bytes(), ParseOptions::default(), 64),
+            Some(expected)
+        );
+    }
+}
@@ -0,0 +1,81 @@
+use crate::dimacs::{parse_lit, DimacsError, Tokens};
+use crate::Clause;
+
+/// A step of a DRAT proof.
+#[derive(Clone, Debug, PartialEq)]
+pub enum DratStep {
+    /// The clause is added to the formula.
+    Add(Clause),
+    /// The clause is deleted from the formula.
+    Delete(Clause),
+}
+
+/// Parse a proof in the textual DRAT format from buffer reader.
+///
+/// Each step is a `0`-terminated clause, prefixed with `d` for deletions.
+/// The binary DRAT format is not supported.
+pub fn parse_drat_from_buf_reader<F>(reader: &mut F) -> Result<Vec<DratStep>, DimacsError>
+where
+    F: std::io::BufRead,
+{
+    let mut tokens = Tokens::new(reader);
+    let mut steps = vec![];
+    let mut lits = vec![];
+    let mut delete = false;
+    let mut started = false;
+    while let Some(range) = tokens.next_token()? {
+        let line = tokens.line_no();
+        let token = tokens.token(range);
+        if !started && token == "d" {
+            delete = true;
+            started = true;
+            continue;
+        }
+        started = true;
+        match parse_lit(token, line)? {
+            Some(lit) => lits.push(lit),
+            None => {
+                let clause = Clause {
+                    lits: std::mem::take(&mut lits),
+                };
+                steps.push(if delete {
+                    DratStep::Delete(clause)
+                } else {
+                    DratStep::Add(clause)
+                });
+                delete = false;
+                started = false;
+            }
+        }
+    }
+    if started {
+        return Err(DimacsError::UnexpectedEof);
+    }
+    Ok(steps)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Lit;
+
+    #[test]
+    fn parse_drat() {
+        let proof = "c proof\n1 2 0\nd 1 -3 0\n0\n";
+        let clause = |ints: &[i32]| Clause {
+            lits: ints.iter().map(|&i| Lit::from_dimacs(i)).collect(),
+        };
+        assert_eq!(
+            parse_drat_from_buf_reader(&mut proof.as_bytes()).unwrap(),
+            vec![
+                DratStep::Add(clause(&[1, 2])),
+                DratStep::Delete(clause(&[1, -3])),
+                DratStep::Add(clause(&[])),
+            ]
+        );
+        assert!(matches!(
+            parse_drat_from_buf_reader(&mut "1 x 0\n".as_bytes()),
+            Err(DimacsError::InvalidLiteral { line: 1, .. })
+        ));
+    }
+}
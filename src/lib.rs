@@ -2,26 +2,69 @@
 
 //! Common types used in SolHOP.
 
+use std::collections::HashSet;
+use std::convert::TryFrom;
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::iter::FromIterator;
 use std::ops::Not;
+use std::str::FromStr;
 
+/// Clause database module
+pub mod clause_db;
 /// Dimacs module
 pub mod dimacs;
+/// DRAT proof module
+pub mod drat;
+/// 2-SAT solver module
+pub mod two_sat;
 
 /// A variable.
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+///
+/// Variables are ordered by their index. With the `serde` feature a variable
+/// is serialized as its 0-based index, unlike a [`Lit`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Var(usize);
 
 impl Var {
-    /// Create new var TODO: Make it private
-    pub fn new(index: usize) -> Self {
+    /// Create new var.
+    pub(crate) fn new(index: usize) -> Self {
         Self(index)
     }
 
+    /// Create the var with the given index. This is the intended way to build
+    /// variables, indices start at 0.
+    pub fn from_index(index: usize) -> Self {
+        Self::new(index)
+    }
+
+    /// Create the var from its raw value, the inverse of [`Var::index`].
+    /// Same as [`Var::from_index`].
+    pub fn from_raw(raw: usize) -> Self {
+        Self::new(raw)
+    }
+
+    /// Create the var numbered `n` in dimacs, i.e. the var with index `n - 1`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `n` is 0.
+    pub fn from_dimacs(n: u32) -> Self {
+        assert_ne!(n, 0, "dimacs variables start at 1");
+        Self((n - 1) as usize)
+    }
+
     /// Returns the actual value stored inside that can be used to index arrays.
     pub fn index(self) -> usize {
         self.0
     }
 
+    /// Returns an iterator over the `n` variables with indices `0..n`.
+    pub fn range(n: usize) -> impl DoubleEndedIterator<Item = Var> + ExactSizeIterator {
+        (0..n).map(Var)
+    }
+
     /// Create positive literal from variable.
     pub fn pos_lit(self) -> Lit {
         Lit::new(self, false)
@@ -34,7 +77,10 @@ impl Var {
 }
 
 /// A literal.
-#[derive(Clone, Copy, PartialEq, Debug)]
+///
+/// Literals are ordered by their index, so they are sorted by variable first
+/// and the positive literal of a variable comes right before its negation.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
 pub struct Lit(usize);
 
 /// Placeholder Lit
@@ -56,10 +102,185 @@ impl Lit {
         self.0
     }
 
+    /// Create the lit from its raw value, the inverse of [`Lit::index`].
+    ///
+    /// The raw value is the packed `2 * var + sign` representation, not a
+    /// dimacs integer, for which there is [`Lit::from_dimacs`].
+    pub fn from_raw(raw: usize) -> Lit {
+        Lit(raw)
+    }
+
+    /// Returns the index of the literal's variable, same as
+    /// `self.var().index()`.
+    pub fn var_index(self) -> usize {
+        self.0 >> 1
+    }
+
     /// Create lit from var and sign
     pub fn new(var: Var, sign: bool) -> Lit {
         Lit(var.0 + var.0 + (sign as usize))
     }
+
+    /// Create the positive literal of the var with the given index. Same as
+    /// `Var::from_index(index).pos_lit()`.
+    pub fn positive(index: usize) -> Lit {
+        Var::from_index(index).pos_lit()
+    }
+
+    /// Create the negative literal of the var with the given index. Same as
+    /// `Var::from_index(index).neg_lit()`.
+    pub fn negative(index: usize) -> Lit {
+        Var::from_index(index).neg_lit()
+    }
+
+    /// Returns both literals of the variable, positive first.
+    pub fn all_for(var: Var) -> [Lit; 2] {
+        [var.pos_lit(), var.neg_lit()]
+    }
+
+    /// Create lit from a dimacs integer, i.e. `n` is the positive literal of
+    /// variable `n - 1` and `-n` its negation.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `i` is not a valid literal, see [`Lit::try_from_dimacs`].
+    pub fn from_dimacs(i: i32) -> Lit {
+        match Lit::try_from_dimacs(i) {
+            Ok(lit) => lit,
+            Err(e) => panic!("{}: {}", e, i),
+        }
+    }
+
+    /// Create lit from a dimacs integer, failing on 0 and on `i32::MIN`, whose
+    /// magnitude cannot be written back as an `i32` and which would collide
+    /// with [`UNDEF_LIT`] on 32-bit targets.
+    pub fn try_from_dimacs(i: i32) -> Result<Lit, LitError> {
+        match i {
+            0 => Err(LitError::Zero),
+            i32::MIN => Err(LitError::OutOfRange),
+            i => Ok(Lit::new(Var((i.unsigned_abs() - 1) as usize), i < 0)),
+        }
+    }
+
+    /// Returns the dimacs integer for the literal. Inverse of [`Lit::from_dimacs`].
+    ///
+    /// # Panics
+    ///
+    /// Panics if the variable number does not fit in an `i32`.
+    pub fn to_dimacs(self) -> i32 {
+        let var = i32::try_from(self.var().0 + 1).expect("variable out of dimacs range");
+        if self.sign() {
+            -var
+        } else {
+            var
+        }
+    }
+
+    /// Returns the 1-based dimacs variable number, `var().index() + 1`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the variable number does not fit in a `u32`.
+    pub fn dimacs_var(self) -> u32 {
+        u32::try_from(self.var_index() + 1).expect("variable out of dimacs range")
+    }
+
+    /// Returns the dimacs sign of the literal, `-1` if negative and `1` if
+    /// positive, so that `dimacs_sign() * dimacs_var()` is the dimacs integer.
+    pub fn dimacs_sign(self) -> i32 {
+        if self.sign() {
+            -1
+        } else {
+            1
+        }
+    }
+}
+
+/// Error returned when an integer is not a valid dimacs literal.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum LitError {
+    /// 0 terminates clauses and is not a literal.
+    Zero,
+    /// The magnitude is too large to be represented.
+    OutOfRange,
+}
+
+impl fmt::Display for LitError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LitError::Zero => write!(f, "0 is not a valid literal"),
+            LitError::OutOfRange => write!(f, "literal out of range"),
+        }
+    }
+}
+
+impl std::error::Error for LitError {}
+
+/// Error returned when parsing a literal from a string fails.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum ParseLitError {
+    /// The string is not an integer.
+    InvalidInteger(std::num::ParseIntError),
+    /// The integer is not a valid literal.
+    InvalidLit(LitError),
+}
+
+impl fmt::Display for ParseLitError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ParseLitError::InvalidInteger(e) => write!(f, "invalid literal: {}", e),
+            ParseLitError::InvalidLit(e) => write!(f, "invalid literal: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for ParseLitError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ParseLitError::InvalidInteger(e) => Some(e),
+            ParseLitError::InvalidLit(e) => Some(e),
+        }
+    }
+}
+
+impl fmt::Display for Lit {
+    /// Formats the literal as its dimacs integer, e.g. `3` or `-3`.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.sign() {
+            write!(f, "-")?;
+        }
+        write!(f, "{}", self.var().0 + 1)
+    }
+}
+
+impl FromStr for Lit {
+    type Err = ParseLitError;
+
+    /// Parses a literal from its dimacs integer, e.g. `"-3"`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let i = s.parse::<i32>().map_err(ParseLitError::InvalidInteger)?;
+        Lit::try_from_dimacs(i).map_err(ParseLitError::InvalidLit)
+    }
+}
+
+/// Literals are serialized as their dimacs integer rather than the raw index,
+/// so their variable is 1-based, unlike a serialized [`Var`]. A literal whose
+/// variable does not fit in an `i32` fails to serialize.
+#[cfg(feature = "serde")]
+impl serde::Serialize for Lit {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let var = i32::try_from(self.var().0 + 1)
+            .map_err(|_| serde::ser::Error::custom(LitError::OutOfRange))?;
+        serializer.serialize_i32(if self.sign() { -var } else { var })
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for Lit {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let i = <i32 as serde::Deserialize>::deserialize(deserializer)?;
+        Lit::try_from_dimacs(i).map_err(serde::de::Error::custom)
+    }
 }
 
 impl Not for Lit {
@@ -71,8 +292,59 @@ impl Not for Lit {
     }
 }
 
+/// Types that can index arrays, i.e. `Var` and `Lit`, for storage that is
+/// generic over the key type.
+pub trait Indexed {
+    /// Returns the index, same as the inherent `index` method.
+    fn index(&self) -> usize;
+}
+
+impl Indexed for Var {
+    fn index(&self) -> usize {
+        Var::index(*self)
+    }
+}
+
+impl Indexed for Lit {
+    fn index(&self) -> usize {
+        Lit::index(*self)
+    }
+}
+
+/// Conversion into a literal, used by the clause building APIs.
+///
+/// Implemented for `Lit`, for `Var` giving its positive literal and for `i32`
+/// as a dimacs integer, so that e.g. `db.add_clause([1, -2, 3])` works.
+pub trait IntoLit {
+    /// Convert into a literal.
+    ///
+    /// # Panics
+    ///
+    /// The `i32` implementation panics like [`Lit::from_dimacs`].
+    fn into_lit(self) -> Lit;
+}
+
+impl IntoLit for Lit {
+    fn into_lit(self) -> Lit {
+        self
+    }
+}
+
+impl IntoLit for Var {
+    fn into_lit(self) -> Lit {
+        self.pos_lit()
+    }
+}
+
+impl IntoLit for i32 {
+    fn into_lit(self) -> Lit {
+        Lit::from_dimacs(self)
+    }
+}
+
 /// A Lifted boolean.
 #[derive(Clone, Copy, PartialEq, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum LBool {
     /// Represents True.
     True,
@@ -82,6 +354,99 @@ pub enum LBool {
     Undef,
 }
 
+impl LBool {
+    /// Three-valued conjunction following Kleene semantics.
+    ///
+    /// | and   | True  | False | Undef |
+    /// |-------|-------|-------|-------|
+    /// | True  | True  | False | Undef |
+    /// | False | False | False | False |
+    /// | Undef | Undef | False | Undef |
+    pub fn and(self, other: LBool) -> LBool {
+        match (self, other) {
+            (LBool::False, _) | (_, LBool::False) => LBool::False,
+            (LBool::True, LBool::True) => LBool::True,
+            _ => LBool::Undef,
+        }
+    }
+
+    /// Three-valued disjunction following Kleene semantics.
+    ///
+    /// | or    | True  | False | Undef |
+    /// |-------|-------|-------|-------|
+    /// | True  | True  | True  | True  |
+    /// | False | True  | False | Undef |
+    /// | Undef | True  | Undef | Undef |
+    pub fn or(self, other: LBool) -> LBool {
+        match (self, other) {
+            (LBool::True, _) | (_, LBool::True) => LBool::True,
+            (LBool::False, LBool::False) => LBool::False,
+            _ => LBool::Undef,
+        }
+    }
+
+    /// Returns the value of a literal with the given sign whose variable has
+    /// value `self`, i.e. `self` when `sign` is false and `!self` otherwise.
+    /// Undef stays Undef.
+    pub fn xor_sign(self, sign: bool) -> LBool {
+        if sign {
+            !self
+        } else {
+            self
+        }
+    }
+
+    /// Returns true if the value is True.
+    pub fn is_true(self) -> bool {
+        self == LBool::True
+    }
+
+    /// Returns true if the value is False.
+    pub fn is_false(self) -> bool {
+        self == LBool::False
+    }
+
+    /// Returns true if the value is Undef.
+    pub fn is_undef(self) -> bool {
+        self == LBool::Undef
+    }
+
+    /// Returns true if the value is True or False, i.e. not Undef.
+    pub fn is_assigned(self) -> bool {
+        self != LBool::Undef
+    }
+
+    /// Convert LBool to an integer for passing over FFI, mapping False to 0,
+    /// True to 1 and Undef to 2.
+    pub fn to_i8(self) -> i8 {
+        match self {
+            LBool::False => 0,
+            LBool::True => 1,
+            LBool::Undef => 2,
+        }
+    }
+
+    /// Convert an integer from [`LBool::to_i8`] back to LBool, returning None
+    /// for any other integer.
+    pub fn from_i8(i: i8) -> Option<LBool> {
+        match i {
+            0 => Some(LBool::False),
+            1 => Some(LBool::True),
+            2 => Some(LBool::Undef),
+            _ => None,
+        }
+    }
+
+    /// Convert LBool to `Option<bool>`, mapping Undef to None.
+    pub fn as_option(self) -> Option<bool> {
+        match self {
+            LBool::True => Some(true),
+            LBool::False => Some(false),
+            LBool::Undef => None,
+        }
+    }
+}
+
 impl Not for LBool {
     type Output = Self;
 
@@ -107,15 +472,344 @@ impl From<bool> for LBool {
     }
 }
 
+impl From<Option<bool>> for LBool {
+    /// Convert `Option<bool>` to LBool, mapping None to Undef.
+    fn from(b: Option<bool>) -> Self {
+        match b {
+            Some(b) => b.into(),
+            None => LBool::Undef,
+        }
+    }
+}
+
+/// An assignment of values to variables.
+///
+/// Indexing with a variable outside `0..n_vars` panics.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Assignment {
+    values: Vec<LBool>,
+}
+
+impl Assignment {
+    /// Create an assignment of `n_vars` variables, all Undef.
+    pub fn new(n_vars: usize) -> Self {
+        Assignment {
+            values: vec![LBool::Undef; n_vars],
+        }
+    }
+
+    /// Returns the value of the variable.
+    pub fn value(&self, var: Var) -> LBool {
+        self.values[var.index()]
+    }
+
+    /// Returns the value of the literal, i.e. the value of its variable with
+    /// the sign applied.
+    pub fn value_of_lit(&self, lit: Lit) -> LBool {
+        self.value(lit.var()).xor_sign(lit.sign())
+    }
+
+    /// Assign a value to the variable.
+    pub fn assign(&mut self, var: Var, value: bool) {
+        self.values[var.index()] = value.into();
+    }
+}
+
+/// A model stored as a bit vector, one bit per variable.
+///
+/// This takes an eighth of the memory of a `Vec<bool>`, e.g. about 1.25MB
+/// instead of 10MB for 10M variables. Accessing a variable outside
+/// `0..len()` panics.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(try_from = "PackedModelData"))]
+pub struct PackedModel {
+    words: Vec<u64>,
+    len: usize,
+}
+
+impl PackedModel {
+    /// Create a model of `n_vars` variables, all false.
+    pub fn new(n_vars: usize) -> Self {
+        PackedModel {
+            words: vec![0; n_vars.div_ceil(64)],
+            len: n_vars,
+        }
+    }
+
+    /// Returns the number of variables in the model.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Returns true if the model has no variables.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    fn check(&self, var: Var) -> (usize, u64) {
+        assert!(
+            var.index() < self.len,
+            "variable {} out of range for model of {} variables",
+            var.index(),
+            self.len
+        );
+        (var.index() / 64, 1 << (var.index() % 64))
+    }
+
+    /// Returns the value of the variable.
+    pub fn get(&self, var: Var) -> bool {
+        let (word, mask) = self.check(var);
+        self.words[word] & mask != 0
+    }
+
+    /// Set the value of the variable.
+    pub fn set(&mut self, var: Var, value: bool) {
+        let (word, mask) = self.check(var);
+        if value {
+            self.words[word] |= mask;
+        } else {
+            self.words[word] &= !mask;
+        }
+    }
+
+    /// Create a packed model from a model indexed by variable.
+    pub fn from_bools(model: &[bool]) -> Self {
+        let mut packed = PackedModel::new(model.len());
+        for (word, chunk) in packed.words.iter_mut().zip(model.chunks(64)) {
+            for (i, &value) in chunk.iter().enumerate() {
+                *word |= (value as u64) << i;
+            }
+        }
+        packed
+    }
+
+    /// Returns the model as one bool per variable.
+    pub fn to_bools(&self) -> Vec<bool> {
+        Var::range(self.len).map(|var| self.get(var)).collect()
+    }
+}
+
+/// Unchecked fields of a deserialized [`PackedModel`].
+#[cfg(feature = "serde")]
+#[derive(serde::Deserialize)]
+struct PackedModelData {
+    words: Vec<u64>,
+    len: usize,
+}
+
+#[cfg(feature = "serde")]
+impl TryFrom<PackedModelData> for PackedModel {
+    type Error = String;
+
+    /// Checks that there is one word per 64 variables and that the bits past
+    /// `len` are clear, as equality and hashing compare whole words.
+    fn try_from(data: PackedModelData) -> Result<Self, Self::Error> {
+        let PackedModelData { words, len } = data;
+        if words.len() != len.div_ceil(64) {
+            return Err(format!("{} words for {} variables", words.len(), len));
+        }
+        if len % 64 != 0 && words.last().is_some_and(|&w| w >> (len % 64) != 0) {
+            return Err(format!("bits set past {} variables", len));
+        }
+        Ok(PackedModel { words, len })
+    }
+}
+
 /// A Clause.
+///
+/// Clauses compare as sets of literals: equality and hashing ignore the
+/// order of literals and repeated literals.
 #[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Clause {
     /// A vector of literals forming the clause.
     pub lits: Vec<Lit>,
 }
 
+impl Clause {
+    /// Create the empty clause, which is false under every model.
+    pub fn empty() -> Clause {
+        Clause { lits: vec![] }
+    }
+
+    /// Create a clause from dimacs integers, e.g. `&[1, -2, 0]`. A single
+    /// trailing `0` is skipped.
+    ///
+    /// # Panics
+    ///
+    /// Panics if any other integer is 0 or out of range, see
+    /// [`Lit::from_dimacs`].
+    pub fn from_dimacs_ints(ints: &[i32]) -> Clause {
+        let ints = match ints {
+            [rest @ .., 0] => rest,
+            _ => ints,
+        };
+        ints.iter().map(|&i| Lit::from_dimacs(i)).collect()
+    }
+
+    /// Returns an iterator over the literals of the clause.
+    pub fn iter(&self) -> std::slice::Iter<'_, Lit> {
+        self.lits.iter()
+    }
+
+    /// Returns true if the clause has no literals, i.e. it is unsatisfiable.
+    pub fn is_empty(&self) -> bool {
+        self.lits.is_empty()
+    }
+
+    /// Removes all literals. The allocated capacity is kept, so the clause
+    /// can be reused as a buffer without allocating again.
+    pub fn clear(&mut self) {
+        self.lits.clear();
+    }
+
+    /// Appends `lit` to the clause.
+    pub fn push(&mut self, lit: Lit) {
+        self.lits.push(lit);
+    }
+
+    /// Returns the literal of a unit clause, i.e. a clause with exactly one
+    /// literal, and `None` otherwise.
+    pub fn is_unit(&self) -> Option<Lit> {
+        match self.lits[..] {
+            [lit] => Some(lit),
+            _ => None,
+        }
+    }
+
+    /// Resolves two clauses on `pivot`.
+    ///
+    /// The pivot must occur positively in one clause and negatively in the
+    /// other. The resolvent contains the literals of both clauses except the
+    /// resolved pivot literals, without duplicates. Returns `None` if the
+    /// pivot does not occur with opposite signs or the resolvent is a
+    /// tautology.
+    pub fn resolve(a: &Clause, b: &Clause, pivot: Var) -> Option<Clause> {
+        let (pos, neg) = (pivot.pos_lit(), pivot.neg_lit());
+        let (a_lit, b_lit) = if a.lits.contains(&pos) && b.lits.contains(&neg) {
+            (pos, neg)
+        } else if a.lits.contains(&neg) && b.lits.contains(&pos) {
+            (neg, pos)
+        } else {
+            return None;
+        };
+        // Only the resolved literals go, the other polarity of the pivot
+        // stays, e.g. in a clause that is already a tautology on it.
+        let mut resolvent: Clause = a
+            .iter()
+            .filter(|&&lit| lit != a_lit)
+            .chain(b.iter().filter(|&&lit| lit != b_lit))
+            .copied()
+            .collect();
+        resolvent.dedup();
+        if resolvent.is_tautology() {
+            None
+        } else {
+            Some(resolvent)
+        }
+    }
+
+    /// Returns true if `self` subsumes `other`, i.e. every literal of `self`
+    /// occurs in `other`. The subsuming clause is the stronger one, so `other`
+    /// can be dropped. Equal clauses subsume each other.
+    pub fn subsumes(&self, other: &Clause) -> bool {
+        let other: HashSet<_> = other.iter().collect();
+        self.iter().all(|lit| other.contains(lit))
+    }
+
+    /// Returns a copy of the clause with literals sorted and duplicates removed.
+    pub fn canonical(&self) -> Clause {
+        let mut lits = self.lits.clone();
+        lits.sort_unstable();
+        lits.dedup();
+        Clause { lits }
+    }
+
+    /// Sorts the literals and removes duplicates in place, returning true if
+    /// the clause is a tautology.
+    ///
+    /// A literal and its negation are adjacent in the sorted order, so the
+    /// check needs no further pass.
+    pub fn canonicalize(&mut self) -> bool {
+        canonicalize_lits(&mut self.lits)
+    }
+
+    /// Returns true if the clause contains both a literal and its negation.
+    pub fn is_tautology(&self) -> bool {
+        is_tautology_lits(&self.lits)
+    }
+
+    /// Removes repeated literals, keeping the first occurrence of each.
+    /// The order of the remaining literals is preserved.
+    pub fn dedup(&mut self) {
+        let mut seen = HashSet::with_capacity(self.lits.len());
+        self.lits.retain(|&lit| seen.insert(lit));
+    }
+
+    /// Swaps the literals at positions `i` and `j`.
+    ///
+    /// By convention positions 0 and 1 are the watched slots, so this is how a
+    /// new watch is moved into place.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `i` or `j` is out of bounds.
+    pub fn swap_lits(&mut self, i: usize, j: usize) {
+        self.lits.swap(i, j);
+    }
+
+    /// Returns the two watched literals, i.e. the literals at positions 0
+    /// and 1, or None if the clause has fewer than two literals.
+    pub fn watch_literals(&self) -> Option<(Lit, Lit)> {
+        match self.lits[..] {
+            [a, b, ..] => Some((a, b)),
+            _ => None,
+        }
+    }
+
+    /// Sorts the literals by decreasing activity of their variable, where
+    /// `activity` is indexed by variable.
+    ///
+    /// Literals of variables outside `activity` come last. Activities are
+    /// compared with `f64::total_cmp` and the sort is stable.
+    pub fn sort_by_activity(&mut self, activity: &[f64]) {
+        let key = |lit: &Lit| activity.get(lit.var_index());
+        self.lits.sort_by(|a, b| match (key(a), key(b)) {
+            (Some(a), Some(b)) => b.total_cmp(a),
+            (a, b) => b.is_some().cmp(&a.is_some()),
+        });
+    }
+
+    /// Returns the clause with every literal replaced by its negation.
+    ///
+    /// This is literal-wise negation only: the logical negation of a clause is
+    /// the conjunction of the negated literals, not a single clause.
+    pub fn negate_all(&self) -> Clause {
+        self.lits.iter().map(|&lit| !lit).collect()
+    }
+
+    /// Evaluates the clause under a (partial) model indexed by variable.
+    ///
+    /// Returns `True` if some literal is true, `False` if all literals are
+    /// false and `Undef` otherwise. Variables outside the model are `Undef`.
+    pub fn eval(&self, model: &[LBool]) -> LBool {
+        let mut result = LBool::False;
+        for lit in &self.lits {
+            match lit_lvalue(model, *lit) {
+                LBool::True => return LBool::True,
+                LBool::Undef => result = LBool::Undef,
+                LBool::False => {}
+            }
+        }
+        result
+    }
+}
+
 /// Solution to the SAT Formula.
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Solution {
     /// The formula is unsatisfiable.
     Unsat,
@@ -126,3 +820,758 @@ pub enum Solution {
     /// No solution could be found.
     Unknown,
 }
+
+impl fmt::Display for Solution {
+    /// Formats a readable summary such as `SAT [x1, -x2, x3]` or `UNSAT`,
+    /// where `-xi` means that dimacs variable `i` is false. See
+    /// [`dimacs::write_solution`] for the competition output format.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (verdict, model) = match self {
+            Solution::Unsat => return write!(f, "UNSAT"),
+            Solution::Unknown => return write!(f, "UNKNOWN"),
+            Solution::Best(model) => ("BEST", model),
+            Solution::Sat(model) => ("SAT", model),
+        };
+        write!(f, "{} [", verdict)?;
+        for (i, &value) in model.iter().enumerate() {
+            if i > 0 {
+                write!(f, ", ")?;
+            }
+            write!(f, "{}x{}", if value { "" } else { "-" }, i + 1)?;
+        }
+        write!(f, "]")
+    }
+}
+
+impl<'a> IntoIterator for &'a Clause {
+    type Item = &'a Lit;
+    type IntoIter = std::slice::Iter<'a, Lit>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.lits.iter()
+    }
+}
+
+impl IntoIterator for Clause {
+    type Item = Lit;
+    type IntoIter = std::vec::IntoIter<Lit>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.lits.into_iter()
+    }
+}
+
+impl FromIterator<Lit> for Clause {
+    fn from_iter<I: IntoIterator<Item = Lit>>(iter: I) -> Self {
+        Clause {
+            lits: iter.into_iter().collect(),
+        }
+    }
+}
+
+impl PartialEq for Clause {
+    fn eq(&self, other: &Self) -> bool {
+        self.canonical().lits == other.canonical().lits
+    }
+}
+
+impl Eq for Clause {}
+
+impl Hash for Clause {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.canonical().lits.hash(state);
+    }
+}
+
+/// Returns the value of the literal under a model indexed by variable, or
+/// None if its variable is outside the model.
+pub fn lit_value(model: &[bool], lit: Lit) -> Option<bool> {
+    model.get(lit.var_index()).map(|&value| value != lit.sign())
+}
+
+/// Returns the value of the literal under a (partial) model indexed by
+/// variable, or Undef if its variable is outside the model.
+pub fn lit_lvalue(model: &[LBool], lit: Lit) -> LBool {
+    model
+        .get(lit.var_index())
+        .map_or(LBool::Undef, |value| value.xor_sign(lit.sign()))
+}
+
+/// Returns the number of variables covering `lits`, one more than the largest
+/// variable index, or 0 without literals.
+///
+/// Anything indexed by the variables of a parsed formula is sized with this
+/// rather than with the declared variable count, which a header may state
+/// arbitrarily large.
+pub(crate) fn n_vars_covering<'a>(lits: impl IntoIterator<Item = &'a Lit>) -> usize {
+    lits.into_iter()
+        .map(|lit| lit.var_index() + 1)
+        .fold(0, usize::max)
+}
+
+/// See [`Clause::canonicalize`].
+pub(crate) fn canonicalize_lits(lits: &mut Vec<Lit>) -> bool {
+    lits.sort_unstable();
+    lits.dedup();
+    lits.windows(2).any(|w| w[0].var() == w[1].var())
+}
+
+/// See [`Clause::is_tautology`].
+pub(crate) fn is_tautology_lits(lits: &[Lit]) -> bool {
+    let mut seen = HashSet::with_capacity(lits.len());
+    for &lit in lits {
+        if seen.contains(&!lit) {
+            return true;
+        }
+        seen.insert(lit);
+    }
+    false
+}
+
+/// Returns true if some literal is true under `model`. Variables outside the
+/// model count as false.
+pub(crate) fn satisfies(model: &[bool], lits: &[Lit]) -> bool {
+    lits.iter().any(|&lit| lit_value(model, lit) == Some(true))
+}
+
+impl Solution {
+    /// Returns the model of a `Sat` or `Best` solution, None otherwise.
+    pub fn model(&self) -> Option<&[bool]> {
+        match self {
+            Solution::Sat(model) | Solution::Best(model) => Some(model),
+            Solution::Unsat | Solution::Unknown => None,
+        }
+    }
+
+    /// Returns the solution with its model resized to `n_vars` variables,
+    /// padding with `default`. A longer model is truncated, dropping the
+    /// variables beyond `n_vars`. `Unsat` and `Unknown` are returned as is.
+    pub fn fill_to(&self, n_vars: usize, default: bool) -> Solution {
+        let fill = |model: &[bool]| {
+            let mut model = model.to_vec();
+            model.resize(n_vars, default);
+            model
+        };
+        match self {
+            Solution::Sat(model) => Solution::Sat(fill(model)),
+            Solution::Best(model) => Solution::Best(fill(model)),
+            Solution::Unsat => Solution::Unsat,
+            Solution::Unknown => Solution::Unknown,
+        }
+    }
+
+    /// Returns the solution with every variable of the model flipped.
+    /// `Unsat` and `Unknown` are returned as is.
+    pub fn flipped(&self) -> Solution {
+        let flip = |model: &[bool]| model.iter().map(|&b| !b).collect();
+        match self {
+            Solution::Sat(model) => Solution::Sat(flip(model)),
+            Solution::Best(model) => Solution::Best(flip(model)),
+            Solution::Unsat => Solution::Unsat,
+            Solution::Unknown => Solution::Unknown,
+        }
+    }
+
+    /// Returns true for proven results, i.e. `Sat` and `Unsat`, and false for
+    /// the best-effort `Best` and `Unknown`.
+    pub fn is_definitive(&self) -> bool {
+        matches!(self, Solution::Sat(_) | Solution::Unsat)
+    }
+
+    /// Returns the model of a `Sat` solution as a packed model, None for any
+    /// other variant.
+    pub fn to_packed(&self) -> Option<PackedModel> {
+        match self {
+            Solution::Sat(model) => Some(PackedModel::from_bools(model)),
+            _ => None,
+        }
+    }
+
+    /// Checks that a `Sat` model satisfies the formula.
+    ///
+    /// For a wcnf only the hard clauses, i.e. those whose weight equals the
+    /// hard weight, need to be satisfied. Returns false for any other
+    /// variant or if the model length differs from the number of variables.
+    pub fn verify(&self, dimacs: &dimacs::Dimacs) -> bool {
+        let model = match self {
+            Solution::Sat(model) => model,
+            _ => return false,
+        };
+        let satisfied = |lits: &[Lit]| satisfies(model, lits);
+        match dimacs {
+            dimacs::Dimacs::Cnf { n_vars, clauses } => {
+                model.len() == *n_vars && clauses.iter().all(|c| satisfied(c))
+            }
+            dimacs::Dimacs::Wcnf {
+                n_vars,
+                clauses,
+                hard_weight,
+            } => {
+                model.len() == *n_vars
+                    && clauses
+                        .iter()
+                        .all(|(c, weight)| !dimacs::is_hard(*weight, *hard_weight) || satisfied(c))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn dimacs_lits() {
+        assert_eq!(Lit::from_dimacs(3), Var::new(2).pos_lit());
+        assert_eq!(Lit::from_dimacs(-1), Var::new(0).neg_lit());
+        for &i in &[1, -1, 42, -42, i32::MAX, -i32::MAX] {
+            assert_eq!(Lit::from_dimacs(i).to_dimacs(), i);
+        }
+        assert_eq!(Lit::try_from_dimacs(0), Err(LitError::Zero));
+        assert_eq!(Lit::try_from_dimacs(i32::MIN), Err(LitError::OutOfRange));
+        assert_eq!(Lit::try_from_dimacs(-5), Ok(Var::new(4).neg_lit()));
+    }
+
+    #[test]
+    fn dimacs_var_sign() {
+        assert_eq!(Var::new(0).pos_lit().dimacs_var(), 1);
+        assert_eq!(Var::new(0).pos_lit().dimacs_sign(), 1);
+        assert_eq!(Var::new(6).neg_lit().dimacs_var(), 7);
+        assert_eq!(Var::new(6).neg_lit().dimacs_sign(), -1);
+        for &i in &[1, -1, 42, -42, i32::MAX, -i32::MAX] {
+            let lit = Lit::from_dimacs(i);
+            assert_eq!(lit.dimacs_sign() * lit.dimacs_var() as i32, i);
+        }
+    }
+
+    #[test]
+    fn lit_ordering() {
+        let mut lits = vec![
+            Lit::from_dimacs(-2),
+            Lit::from_dimacs(1),
+            Lit::from_dimacs(2),
+            Lit::from_dimacs(-1),
+        ];
+        lits.sort();
+        assert_eq!(
+            lits,
+            vec![
+                Lit::from_dimacs(1),
+                Lit::from_dimacs(-1),
+                Lit::from_dimacs(2),
+                Lit::from_dimacs(-2)
+            ]
+        );
+        assert!(Var::new(0) < Var::new(1));
+    }
+
+    #[test]
+    fn lit_display_from_str() {
+        assert_eq!(Lit::from_dimacs(3).to_string(), "3");
+        assert_eq!(Lit::from_dimacs(-3).to_string(), "-3");
+        assert_eq!("-3".parse::<Lit>(), Ok(Var::new(2).neg_lit()));
+        assert_eq!(
+            "0".parse::<Lit>(),
+            Err(ParseLitError::InvalidLit(LitError::Zero))
+        );
+        assert!(matches!(
+            "x1".parse::<Lit>(),
+            Err(ParseLitError::InvalidInteger(_))
+        ));
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serde_roundtrip() {
+        let lit = Lit::from_dimacs(-3);
+        assert_eq!(serde_json::to_string(&lit).unwrap(), "-3");
+        assert_eq!(serde_json::from_str::<Lit>("-3").unwrap(), lit);
+        assert!(serde_json::from_str::<Lit>("0").is_err());
+        assert!(serde_json::to_string(&Var::new(i32::MAX as usize).neg_lit()).is_err());
+        assert_eq!(serde_json::to_string(&Var::new(2)).unwrap(), "2");
+
+        let clause = Clause {
+            lits: vec![Lit::from_dimacs(1), Lit::from_dimacs(-2)],
+        };
+        let json = serde_json::to_string(&clause).unwrap();
+        assert_eq!(json, r#"{"lits":[1,-2]}"#);
+        assert_eq!(
+            serde_json::from_str::<Clause>(&json).unwrap().lits,
+            clause.lits
+        );
+
+        let solution = Solution::Sat(vec![true, false]);
+        let json = serde_json::to_string(&solution).unwrap();
+        assert_eq!(serde_json::from_str::<Solution>(&json).unwrap(), solution);
+
+        let lbool = LBool::Undef;
+        let json = serde_json::to_string(&lbool).unwrap();
+        assert_eq!(serde_json::from_str::<LBool>(&json).unwrap(), lbool);
+
+        let dimacs = dimacs::Dimacs::Wcnf {
+            n_vars: 2,
+            clauses: vec![(vec![Lit::from_dimacs(2)], 3)],
+            hard_weight: Some(4),
+        };
+        let json = serde_json::to_string(&dimacs).unwrap();
+        assert_eq!(
+            serde_json::from_str::<dimacs::Dimacs>(&json).unwrap(),
+            dimacs
+        );
+
+        let packed = PackedModel::from_bools(&[true, false, true]);
+        let json = serde_json::to_string(&packed).unwrap();
+        assert_eq!(json, r#"{"words":[5],"len":3}"#);
+        assert_eq!(serde_json::from_str::<PackedModel>(&json).unwrap(), packed);
+        for bad in &[
+            r#"{"words":[5],"len":65}"#,
+            r#"{"words":[],"len":3}"#,
+            r#"{"words":[5,0],"len":3}"#,
+            r#"{"words":[13],"len":3}"#,
+        ] {
+            assert!(serde_json::from_str::<PackedModel>(bad).is_err(), "{}", bad);
+        }
+        let full = format!(r#"{{"words":[{}],"len":64}}"#, u64::MAX);
+        assert!(serde_json::from_str::<PackedModel>(&full).is_ok());
+    }
+
+    fn clause(ints: &[i32]) -> Clause {
+        Clause::from_dimacs_ints(ints)
+    }
+
+    #[test]
+    fn clause_tautology() {
+        assert!(clause(&[1, -2, 2]).is_tautology());
+        assert!(!clause(&[1, 1, -2, -2]).is_tautology());
+        assert!(!clause(&[]).is_tautology());
+    }
+
+    #[test]
+    fn clause_dedup() {
+        let mut c = clause(&[1, 1, -2, 3, 3]);
+        c.dedup();
+        assert_eq!(c.lits, clause(&[1, -2, 3]).lits);
+    }
+
+    #[test]
+    fn clause_canonicalize() {
+        let mut c = clause(&[3, -1, 3, 2]);
+        assert!(!c.canonicalize());
+        assert_eq!(c.lits, clause(&[-1, 2, 3]).lits);
+        assert_eq!(c, c.canonical());
+
+        let mut c = clause(&[2, 1, -2, 2]);
+        assert!(c.canonicalize());
+        assert_eq!(c.lits, clause(&[1, 2, -2]).lits);
+
+        let mut c = clause(&[-4, -4]);
+        assert!(!c.canonicalize());
+        assert_eq!(c.lits, clause(&[-4]).lits);
+        assert!(!Clause::empty().canonicalize());
+    }
+
+    #[test]
+    fn clause_eval() {
+        use LBool::*;
+        let c = clause(&[1, -2]);
+        assert_eq!(c.eval(&[False, False]), True);
+        assert_eq!(c.eval(&[False, True]), False);
+        assert_eq!(c.eval(&[Undef, True]), Undef);
+        assert_eq!(c.eval(&[False]), Undef);
+        assert_eq!(clause(&[]).eval(&[]), False);
+    }
+
+    #[test]
+    fn solution_verify() {
+        let cnf = dimacs::Dimacs::Cnf {
+            n_vars: 2,
+            clauses: vec![clause(&[1, 2]).lits, clause(&[-1]).lits],
+        };
+        assert!(Solution::Sat(vec![false, true]).verify(&cnf));
+        assert!(!Solution::Sat(vec![true, true]).verify(&cnf));
+        assert!(!Solution::Sat(vec![false, true, false]).verify(&cnf));
+        assert!(!Solution::Unsat.verify(&cnf));
+
+        let wcnf = dimacs::Dimacs::Wcnf {
+            n_vars: 1,
+            clauses: vec![(clause(&[1]).lits, 10), (clause(&[-1]).lits, 1)],
+            hard_weight: Some(10),
+        };
+        assert!(Solution::Sat(vec![true]).verify(&wcnf));
+        assert!(!Solution::Sat(vec![false]).verify(&wcnf));
+    }
+
+    #[test]
+    fn lbool_kleene() {
+        use LBool::*;
+        let values = [True, False, Undef];
+        let and = [
+            [True, False, Undef],
+            [False, False, False],
+            [Undef, False, Undef],
+        ];
+        let or = [
+            [True, True, True],
+            [True, False, Undef],
+            [True, Undef, Undef],
+        ];
+        for (i, &a) in values.iter().enumerate() {
+            for (j, &b) in values.iter().enumerate() {
+                assert_eq!(a.and(b), and[i][j], "{:?} and {:?}", a, b);
+                assert_eq!(a.or(b), or[i][j], "{:?} or {:?}", a, b);
+            }
+        }
+    }
+
+    #[test]
+    fn lbool_option() {
+        for &b in &[Some(true), Some(false), None] {
+            assert_eq!(LBool::from(b).as_option(), b);
+        }
+        assert_eq!(LBool::from(None), LBool::Undef);
+    }
+
+    #[test]
+    fn lbool_xor_sign() {
+        use LBool::*;
+        assert_eq!(True.xor_sign(false), True);
+        assert_eq!(True.xor_sign(true), False);
+        assert_eq!(False.xor_sign(true), True);
+        assert_eq!(Undef.xor_sign(true), Undef);
+        assert_eq!(Undef.xor_sign(false), Undef);
+    }
+
+    #[test]
+    fn assignment() {
+        let mut a = Assignment::new(2);
+        let x = Var::new(1);
+        assert_eq!(a.value(x), LBool::Undef);
+        assert_eq!(a.value_of_lit(x.neg_lit()), LBool::Undef);
+        a.assign(x, false);
+        assert_eq!(a.value(x), LBool::False);
+        assert_eq!(a.value_of_lit(x.pos_lit()), LBool::False);
+        assert_eq!(a.value_of_lit(x.neg_lit()), LBool::True);
+        assert_eq!(a.value(Var::new(0)), LBool::Undef);
+    }
+
+    #[test]
+    fn var_constructors() {
+        assert_eq!(Var::from_index(4).index(), 4);
+        assert_eq!(Var::from_dimacs(1), Var::from_index(0));
+        assert_eq!(Var::from_dimacs(5).pos_lit(), Lit::from_dimacs(5));
+    }
+
+    #[test]
+    fn clause_set_equality() {
+        assert_eq!(
+            clause(&[3, -1, 2]).canonical().lits,
+            clause(&[-1, 2, 3]).lits
+        );
+        assert_eq!(clause(&[1, -2, 1]), clause(&[-2, 1]));
+        assert_ne!(clause(&[1, 2]), clause(&[1, -2]));
+        let set: HashSet<Clause> = vec![clause(&[1, 2]), clause(&[2, 1]), clause(&[2, 2, 1])]
+            .into_iter()
+            .collect();
+        assert_eq!(set.len(), 1);
+    }
+
+    #[test]
+    fn clause_iter() {
+        let c: Clause = [1, -2, 3].iter().map(|&i| Lit::from_dimacs(i)).collect();
+        let vars: Vec<_> = c.iter().map(|l| l.var_index()).collect();
+        assert_eq!(vars, vec![0, 1, 2]);
+        assert_eq!((&c).into_iter().count(), 3);
+        assert_eq!(c.into_iter().last(), Some(Lit::from_dimacs(3)));
+    }
+
+    #[test]
+    fn clause_empty_unit() {
+        assert!(clause(&[]).is_empty());
+        assert!(!clause(&[1]).is_empty());
+        assert_eq!(clause(&[]).is_unit(), None);
+        assert_eq!(clause(&[-4]).is_unit(), Some(Lit::from_dimacs(-4)));
+        assert_eq!(clause(&[1, 2]).is_unit(), None);
+    }
+
+    #[test]
+    fn clause_resolve() {
+        let x = Var::new(0);
+        assert_eq!(
+            Clause::resolve(&clause(&[1, 2, 3]), &clause(&[-1, 2, -4]), x),
+            Some(clause(&[2, 3, -4]))
+        );
+        assert_eq!(
+            Clause::resolve(&clause(&[-1]), &clause(&[1]), x),
+            Some(clause(&[]))
+        );
+        assert_eq!(
+            Clause::resolve(&clause(&[1, 2]), &clause(&[-1, -2]), x),
+            None
+        );
+        assert_eq!(Clause::resolve(&clause(&[1, 2]), &clause(&[1, 3]), x), None);
+        assert_eq!(Clause::resolve(&clause(&[2]), &clause(&[-1]), x), None);
+        assert_eq!(
+            Clause::resolve(&clause(&[1, -1, 2]), &clause(&[-1]), x),
+            Some(clause(&[-1, 2]))
+        );
+        assert_eq!(
+            Clause::resolve(&clause(&[-1, -1, 2]), &clause(&[1, 1]), x),
+            Some(clause(&[2]))
+        );
+        assert_eq!(
+            Clause::resolve(&clause(&[1, -1]), &clause(&[-1, 1]), x),
+            None
+        );
+    }
+
+    #[test]
+    fn clause_subsumes() {
+        assert!(clause(&[1, -2]).subsumes(&clause(&[3, -2, 1])));
+        assert!(!clause(&[3, -2, 1]).subsumes(&clause(&[1, -2])));
+        assert!(clause(&[1, 2]).subsumes(&clause(&[2, 1])));
+        assert!(clause(&[2, 1]).subsumes(&clause(&[1, 2])));
+        assert!(!clause(&[1, 2]).subsumes(&clause(&[1, -2])));
+        assert!(clause(&[]).subsumes(&clause(&[1])));
+    }
+
+    #[test]
+    fn var_range() {
+        let vars: Vec<_> = Var::range(3).collect();
+        assert_eq!(vars, vec![Var::new(0), Var::new(1), Var::new(2)]);
+        assert_eq!(Var::range(0).count(), 0);
+        assert_eq!(
+            Lit::all_for(Var::new(1)),
+            [Lit::from_dimacs(2), Lit::from_dimacs(-2)]
+        );
+    }
+
+    #[test]
+    fn clause_negate_all() {
+        assert_eq!(
+            clause(&[1, -2, 3]).negate_all().lits,
+            clause(&[-1, 2, -3]).lits
+        );
+        assert!(clause(&[]).negate_all().is_empty());
+    }
+
+    #[test]
+    fn lit_from_index() {
+        assert_eq!(Lit::positive(2), Var::new(2).pos_lit());
+        assert_eq!(Lit::negative(2), Var::new(2).neg_lit());
+        assert_eq!(Lit::negative(0), Lit::from_dimacs(-1));
+        assert_eq!(!Lit::positive(7), Lit::negative(7));
+    }
+
+    #[test]
+    fn raw_roundtrip() {
+        for &i in &[1, -1, 5, -5, 1000] {
+            let lit = Lit::from_dimacs(i);
+            assert_eq!(Lit::from_raw(lit.index()), lit);
+            assert_eq!(Var::from_raw(lit.var().index()), lit.var());
+        }
+        assert_eq!(Lit::from_raw(0), Lit::positive(0));
+        assert_eq!(Lit::from_raw(3), Lit::negative(1));
+        assert_eq!(Lit::from_raw(UNDEF_LIT.index()), UNDEF_LIT);
+    }
+
+    #[test]
+    fn clause_watches() {
+        let mut c = clause(&[1, -2, 3]);
+        assert_eq!(
+            c.watch_literals(),
+            Some((Lit::from_dimacs(1), Lit::from_dimacs(-2)))
+        );
+        c.swap_lits(1, 2);
+        assert_eq!(
+            c.watch_literals(),
+            Some((Lit::from_dimacs(1), Lit::from_dimacs(3)))
+        );
+        assert_eq!(clause(&[1]).watch_literals(), None);
+        assert_eq!(clause(&[]).watch_literals(), None);
+    }
+
+    #[test]
+    fn packed_model() {
+        let mut model = PackedModel::new(130);
+        assert_eq!(model.len(), 130);
+        for &i in &[0, 62, 63, 64, 65, 127, 128, 129] {
+            model.set(Var::new(i), true);
+            assert!(model.get(Var::new(i)));
+        }
+        assert!(!model.get(Var::new(1)));
+        model.set(Var::new(64), false);
+        assert!(!model.get(Var::new(64)));
+        assert!(model.get(Var::new(63)));
+        assert!(model.get(Var::new(65)));
+
+        let bools: Vec<bool> = (0..130).map(|i| i % 3 == 0 || i == 64).collect();
+        let packed = PackedModel::from_bools(&bools);
+        assert_eq!(packed.to_bools(), bools);
+        assert_eq!(
+            Solution::Sat(bools.clone()).to_packed(),
+            Some(packed.clone())
+        );
+        assert_eq!(Solution::Best(bools).to_packed(), None);
+        assert!(PackedModel::from_bools(&[]).is_empty());
+    }
+
+    #[test]
+    #[should_panic]
+    fn packed_model_out_of_range() {
+        PackedModel::new(64).get(Var::new(64));
+    }
+
+    #[test]
+    fn solution_hash() {
+        let mut cache = std::collections::HashMap::new();
+        cache.insert(Solution::Sat(vec![true, false]), 1);
+        cache.insert(Solution::Unsat, 2);
+        assert_eq!(cache.get(&Solution::Sat(vec![true, false])), Some(&1));
+        assert_eq!(cache.get(&Solution::Sat(vec![true, true])), None);
+        assert_eq!(cache.get(&Solution::Unsat), Some(&2));
+        assert_eq!(cache.get(&Solution::Unknown), None);
+    }
+
+    #[test]
+    fn solution_display() {
+        assert_eq!(
+            Solution::Sat(vec![true, false, true]).to_string(),
+            "SAT [x1, -x2, x3]"
+        );
+        assert_eq!(Solution::Best(vec![false]).to_string(), "BEST [-x1]");
+        assert_eq!(Solution::Sat(vec![]).to_string(), "SAT []");
+        assert_eq!(Solution::Unsat.to_string(), "UNSAT");
+        assert_eq!(Solution::Unknown.to_string(), "UNKNOWN");
+    }
+
+    #[test]
+    fn clause_from_dimacs_ints() {
+        let lits = vec![Lit::from_dimacs(1), Lit::from_dimacs(-2)];
+        assert_eq!(Clause::from_dimacs_ints(&[1, -2, 0]).lits, lits);
+        assert_eq!(Clause::from_dimacs_ints(&[1, -2]).lits, lits);
+        assert!(Clause::from_dimacs_ints(&[0]).is_empty());
+        assert!(Clause::from_dimacs_ints(&[]).is_empty());
+    }
+
+    #[test]
+    fn lbool_i8() {
+        for &b in &[LBool::True, LBool::False, LBool::Undef] {
+            assert_eq!(LBool::from_i8(b.to_i8()), Some(b));
+        }
+        assert_eq!(LBool::True.to_i8(), 1);
+        assert_eq!(LBool::False.to_i8(), 0);
+        assert_eq!(LBool::Undef.to_i8(), 2);
+        assert_eq!(LBool::from_i8(-1), None);
+        assert_eq!(LBool::from_i8(3), None);
+    }
+
+    #[test]
+    fn into_lit() {
+        assert_eq!(Lit::from_dimacs(-3).into_lit(), Lit::from_dimacs(-3));
+        assert_eq!(Var::new(2).into_lit(), Lit::from_dimacs(3));
+        assert_eq!((-3).into_lit(), Var::new(2).neg_lit());
+    }
+
+    #[test]
+    fn model_lookup() {
+        let model = [true, false];
+        assert_eq!(lit_value(&model, Lit::from_dimacs(1)), Some(true));
+        assert_eq!(lit_value(&model, Lit::from_dimacs(-1)), Some(false));
+        assert_eq!(lit_value(&model, Lit::from_dimacs(-2)), Some(true));
+        assert_eq!(lit_value(&model, Lit::from_dimacs(3)), None);
+
+        let model = [LBool::True, LBool::Undef];
+        assert_eq!(lit_lvalue(&model, Lit::from_dimacs(-1)), LBool::False);
+        assert_eq!(lit_lvalue(&model, Lit::from_dimacs(-2)), LBool::Undef);
+        assert_eq!(lit_lvalue(&model, Lit::from_dimacs(3)), LBool::Undef);
+    }
+
+    #[test]
+    fn lit_var_index() {
+        for &i in &[1, -1, 7, -7] {
+            let lit = Lit::from_dimacs(i);
+            assert_eq!(lit.var_index(), lit.var().index());
+        }
+        assert_eq!(Lit::from_dimacs(-7).var_index(), 6);
+    }
+
+    #[test]
+    fn clause_sort_by_activity() {
+        let mut c = clause(&[1, -2, 5, 3, -4]);
+        c.sort_by_activity(&[0.5, 2.0, 0.5, 1.0]);
+        assert_eq!(c.lits, clause(&[-2, -4, 1, 3, 5]).lits);
+    }
+
+    #[test]
+    fn lbool_predicates() {
+        let preds = |b: LBool| (b.is_true(), b.is_false(), b.is_undef(), b.is_assigned());
+        assert_eq!(preds(LBool::True), (true, false, false, true));
+        assert_eq!(preds(LBool::False), (false, true, false, true));
+        assert_eq!(preds(LBool::Undef), (false, false, true, false));
+    }
+
+    #[test]
+    fn indexed() {
+        fn index<T: Indexed>(key: T) -> usize {
+            key.index()
+        }
+        assert_eq!(index(Var::new(3)), 3);
+        assert_eq!(index(Lit::from_dimacs(-4)), Lit::from_dimacs(-4).index());
+    }
+
+    #[test]
+    fn solution_model() {
+        let model = vec![true, false];
+        assert_eq!(Solution::Sat(model.clone()).model(), Some(&model[..]));
+        assert_eq!(Solution::Best(model.clone()).model(), Some(&model[..]));
+        assert_eq!(Solution::Unsat.model(), None);
+        assert_eq!(Solution::Unknown.model(), None);
+        assert!(Solution::Sat(model.clone()).is_definitive());
+        assert!(Solution::Unsat.is_definitive());
+        assert!(!Solution::Best(model).is_definitive());
+        assert!(!Solution::Unknown.is_definitive());
+    }
+
+    #[test]
+    fn solution_fill_to() {
+        let sol = Solution::Sat(vec![true, false]);
+        assert_eq!(
+            sol.fill_to(4, true),
+            Solution::Sat(vec![true, false, true, true])
+        );
+        assert_eq!(sol.fill_to(1, true), Solution::Sat(vec![true]));
+        assert_eq!(
+            Solution::Best(vec![true]).fill_to(2, false),
+            Solution::Best(vec![true, false])
+        );
+        assert_eq!(Solution::Unsat.fill_to(3, false), Solution::Unsat);
+    }
+
+    #[test]
+    fn solution_flipped() {
+        let sol = Solution::Sat(vec![true, false, false]);
+        assert_eq!(sol.flipped(), Solution::Sat(vec![false, true, true]));
+        assert_eq!(sol.flipped().flipped(), sol);
+        assert_eq!(
+            Solution::Best(vec![false]).flipped(),
+            Solution::Best(vec![true])
+        );
+        assert_eq!(Solution::Sat(vec![]).flipped(), Solution::Sat(vec![]));
+        assert_eq!(Solution::Unsat.flipped(), Solution::Unsat);
+        assert_eq!(Solution::Unknown.flipped(), Solution::Unknown);
+    }
+
+    #[test]
+    fn clause_clear_push() {
+        let mut c = Clause::empty();
+        c.push(Lit::from_dimacs(1));
+        c.push(Lit::from_dimacs(-2));
+        assert_eq!(c.lits, clause(&[1, -2]).lits);
+        let capacity = c.lits.capacity();
+        c.clear();
+        assert!(c.is_empty());
+        assert_eq!(c.lits.capacity(), capacity);
+        c.push(Lit::from_dimacs(3));
+        assert_eq!(c.is_unit(), Some(Lit::from_dimacs(3)));
+    }
+}
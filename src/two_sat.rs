@@ -0,0 +1,217 @@
+use crate::dimacs::Dimacs;
+use crate::{n_vars_covering, Lit, Solution};
+use std::cmp::Ordering;
+
+/// Largest number of variables a model is built for, so that a bogus header
+/// cannot exhaust memory.
+pub const MAX_MODEL_VARS: usize = 1 << 26;
+
+/// Solve a formula whose clauses have at most two literals.
+///
+/// The implication graph of the binary clauses, with `!a ⇒ a` for a unit
+/// clause `a`, is split into strongly connected components with Tarjan's
+/// algorithm. The formula is unsatisfiable iff a variable and its negation
+/// share a component, otherwise the components give a model.
+///
+/// The model has an entry for each declared variable, as
+/// [`Solution::verify`] expects, and variables in no clause are false. If a
+/// clause uses a variable beyond the declared count, or more than
+/// [`MAX_MODEL_VARS`] variables are declared, there is no such model to return
+/// and a satisfiable formula gives `Unknown`.
+///
+/// Returns `Unknown` if a clause is longer than two literals. A wcnf is
+/// solved for its hard clauses and the model is returned as `Best` if there
+/// are soft clauses, since it need not be optimal for them.
+pub fn solve_2sat(dimacs: &Dimacs) -> Solution {
+    let n_used = n_vars_covering(dimacs.iter_clauses().flatten());
+    let mut graph = vec![vec![]; 2 * n_used];
+    let mut n_hard = 0;
+    for clause in dimacs.hard_clauses() {
+        n_hard += 1;
+        match *clause {
+            [] => return Solution::Unsat,
+            [a] => graph[(!a).index()].push(a.index()),
+            [a, b] => {
+                graph[(!a).index()].push(b.index());
+                graph[(!b).index()].push(a.index());
+            }
+            _ => return Solution::Unknown,
+        }
+    }
+
+    let component = strongly_connected_components(&graph);
+    let value = |var| {
+        let (pos, neg) = (Lit::positive(var), Lit::negative(var));
+        // Components are numbered in reverse topological order, so the
+        // literal whose component comes later in the graph is set true.
+        match component[pos.index()].cmp(&component[neg.index()]) {
+            Ordering::Equal => None,
+            order => Some(order == Ordering::Less),
+        }
+    };
+    let values: Vec<bool> = match (0..n_used).map(value).collect() {
+        Some(values) => values,
+        None => return Solution::Unsat,
+    };
+    let n_vars = dimacs.n_vars();
+    if n_used > n_vars || n_vars > MAX_MODEL_VARS {
+        return Solution::Unknown;
+    }
+    let mut model = vec![false; n_vars];
+    model[..n_used].copy_from_slice(&values);
+    if n_hard < dimacs.n_clauses() {
+        Solution::Best(model)
+    } else {
+        Solution::Sat(model)
+    }
+}
+
+/// Returns the component of each node, numbered in the order Tarjan's
+/// algorithm completes them, which is a reverse topological order.
+fn strongly_connected_components(graph: &[Vec<usize>]) -> Vec<usize> {
+    const UNVISITED: usize = usize::MAX;
+    let mut index = vec![UNVISITED; graph.len()];
+    let mut low = vec![0; graph.len()];
+    let mut component = vec![UNVISITED; graph.len()];
+    let mut on_stack = vec![false; graph.len()];
+    let mut stack = vec![];
+    let mut n_visited = 0;
+    let mut n_components = 0;
+    // The recursion is unrolled into a stack of nodes and their next edge,
+    // so long implication chains cannot overflow the call stack.
+    let mut calls: Vec<(usize, usize)> = vec![];
+    for root in 0..graph.len() {
+        if index[root] != UNVISITED {
+            continue;
+        }
+        calls.push((root, 0));
+        while let Some(&(v, edge)) = calls.last() {
+            if edge == 0 {
+                index[v] = n_visited;
+                low[v] = n_visited;
+                n_visited += 1;
+                stack.push(v);
+                on_stack[v] = true;
+            }
+            if let Some(&w) = graph[v].get(edge) {
+                calls.last_mut().unwrap().1 += 1;
+                if index[w] == UNVISITED {
+                    calls.push((w, 0));
+                } else if on_stack[w] {
+                    low[v] = low[v].min(index[w]);
+                }
+                continue;
+            }
+            calls.pop();
+            if let Some(&(u, _)) = calls.last() {
+                low[u] = low[u].min(low[v]);
+            }
+            if low[v] == index[v] {
+                while let Some(w) = stack.pop() {
+                    on_stack[w] = false;
+                    component[w] = n_components;
+                    if w == v {
+                        break;
+                    }
+                }
+                n_components += 1;
+            }
+        }
+    }
+    component
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dimacs::parse_dimacs_from_str;
+
+    fn solve(s: &str) -> (Dimacs, Solution) {
+        let dimacs = parse_dimacs_from_str(s).unwrap();
+        let solution = solve_2sat(&dimacs);
+        (dimacs, solution)
+    }
+
+    #[test]
+    fn satisfiable() {
+        let (dimacs, solution) = solve("p cnf 4 5\n1 2 0\n-1 3 0\n-3 -2 0\n-4 0\n4 2 0\n");
+        assert!(solution.verify(&dimacs));
+        assert_eq!(solution, Solution::Sat(vec![false, true, false, false]));
+
+        let (dimacs, solution) = solve("p cnf 3 0\n");
+        assert!(solution.verify(&dimacs));
+        assert_eq!(solution.model().unwrap().len(), 3);
+    }
+
+    #[test]
+    fn model_length() {
+        // No model of the declared length covers an undeclared variable.
+        assert_eq!(solve("p cnf 1 2\n1 1 0\n2 -3 0\n").1, Solution::Unknown);
+        let huge = format!("p cnf {} 1\n1 2 0\n", MAX_MODEL_VARS + 1);
+        assert_eq!(solve(&huge).1, Solution::Unknown);
+        // Unsatisfiability does not depend on the model.
+        assert_eq!(solve("p cnf 1 2\n2 0\n-2 0\n").1, Solution::Unsat);
+        let huge = format!("p cnf {} 2\n1 0\n-1 0\n", MAX_MODEL_VARS + 1);
+        assert_eq!(solve(&huge).1, Solution::Unsat);
+    }
+
+    #[test]
+    fn unsatisfiable() {
+        assert_eq!(
+            solve("p cnf 2 4\n1 2 0\n-1 2 0\n1 -2 0\n-1 -2 0\n").1,
+            Solution::Unsat
+        );
+        assert_eq!(solve("p cnf 2 3\n1 0\n-1 2 0\n-2 0\n").1, Solution::Unsat);
+        assert_eq!(solve("p cnf 1 1\n0\n").1, Solution::Unsat);
+    }
+
+    #[test]
+    fn not_2sat() {
+        assert_eq!(solve("p cnf 3 2\n1 2 0\n1 2 3 0\n").1, Solution::Unknown);
+    }
+
+    #[test]
+    fn wcnf() {
+        // A `Best` model satisfies the hard clauses.
+        let hard_model = |solution: &Solution| Solution::Sat(solution.model().unwrap().to_vec());
+        let (dimacs, solution) = solve("p wcnf 2 3 5\n5 1 2 0\n5 -1 0\n1 1 -2 0\n");
+        assert_eq!(solution, Solution::Best(vec![false, true]));
+        assert!(hard_model(&solution).verify(&dimacs));
+        assert_eq!(dimacs.cost(solution.model().unwrap()), Some(1));
+        assert_eq!(solve("p wcnf 1 2 5\n5 1 0\n5 -1 0\n").1, Solution::Unsat);
+        let (dimacs, solution) = solve("p wcnf 2 1 5\n5 1 -2 0\n");
+        assert!(solution.verify(&dimacs));
+    }
+
+    #[test]
+    fn matches_brute_force() {
+        let mut seed = 7u32;
+        let mut next = |n: u32| {
+            seed = seed.wrapping_mul(1_103_515_245).wrapping_add(12345);
+            (seed >> 16) % n
+        };
+        for _ in 0..200 {
+            let n_vars = 1 + next(5) as usize;
+            let n_clauses = next(12) as usize;
+            let mut cnf = format!("p cnf {} {}\n", n_vars, n_clauses);
+            for _ in 0..n_clauses {
+                for _ in 0..1 + next(2) {
+                    let var = 1 + next(n_vars as u32) as i32;
+                    let lit = if next(2) == 0 { var } else { -var };
+                    cnf.push_str(&format!("{} ", lit));
+                }
+                cnf.push_str("0\n");
+            }
+            let (dimacs, solution) = solve(&cnf);
+            let satisfiable = (0..1u32 << n_vars).any(|bits| {
+                let model: Vec<_> = (0..n_vars).map(|i| bits >> i & 1 == 1).collect();
+                Solution::Sat(model).verify(&dimacs)
+            });
+            if satisfiable {
+                assert!(solution.verify(&dimacs), "{}", cnf);
+            } else {
+                assert_eq!(solution, Solution::Unsat, "{}", cnf);
+            }
+        }
+    }
+}